@@ -0,0 +1,186 @@
+//! Command line arguments of cargo-pretty-test.
+//!
+//! Arguments are split into three groups:
+//!
+//! * options of cargo-pretty-test, like `--pretty-format json`, which are also accepted without
+//!   the `pretty-` prefix as long as `cargo test` has no option of the same name
+//! * arguments forwarded to `cargo test`, like `--workspace` or a test name filter
+//! * arguments after `--`, forwarded to test binaries
+
+/// Options of cargo-pretty-test by their names without `--`, and whether each takes a value.
+pub const OWN_OPTIONS: &[(&str, bool)] = &[
+    ("format", true),
+    ("tui", false),
+    ("junit", true),
+    ("from-file", true),
+    ("stream", false),
+    ("libtest-json", false),
+    ("inline-failures", false),
+    ("failure-lines", true),
+    ("rerun-failed", false),
+    ("retries", true),
+    ("fail-on-flaky", false),
+    ("watch", false),
+    ("compare", true),
+    ("only", true),
+    ("hide", true),
+    ("collapse-passing", false),
+    ("module-counts", false),
+    ("sort", true),
+    ("slowest", true),
+    ("icons", true),
+];
+
+/// The prefix that marks an option as the one of cargo-pretty-test.
+pub const PREFIX: &str = "--pretty-";
+
+/// Whether the option of cargo-pretty-test takes a value. `None` means there's no such option.
+pub fn takes_value(name: &str) -> Option<bool> {
+    OWN_OPTIONS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|&(_, value)| value)
+}
+
+/// Values of the option in the arguments to `cargo test`, given as `--name value` or
+/// `--name=value`, where the ones after `--` are for test binaries and skipped.
+pub fn cargo_values<'a>(args: &'a [String], name: &str) -> Vec<&'a str> {
+    let option = format!("--{name}");
+    let mut values = Vec::new();
+    let mut args = args.iter().take_while(|arg| *arg != "--");
+    while let Some(arg) = args.next() {
+        if *arg == option {
+            values.extend(args.next().map(String::as_str));
+        } else if let Some(value) = arg.strip_prefix(&option).and_then(|v| v.strip_prefix('=')) {
+            values.push(value);
+        }
+    }
+    values
+}
+
+/// Parsed command line arguments.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Args {
+    /// Options of cargo-pretty-test in the given order, by their names without `--`
+    /// and with values if they take one.
+    pub own: Vec<(&'static str, Option<String>)>,
+    /// Flags turned off by `--no-<flag>`, which override configured ones.
+    pub negated: Vec<&'static str>,
+    /// Arguments forwarded to `cargo test`.
+    pub cargo: Vec<String>,
+    /// Arguments after `--`, forwarded to test binaries.
+    pub test_bins: Vec<String>,
+    /// `--help` or `-h`.
+    pub help: bool,
+    /// `--version` or `-V`.
+    pub version: bool,
+}
+
+impl Args {
+    /// Parse the arguments after `cargo pretty-test`.
+    pub fn parse<S: Into<String>>(args: impl IntoIterator<Item = S>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.test_bins.extend(args);
+                break;
+            }
+            if arg == "--help" || arg == "-h" {
+                parsed.help = true;
+                continue;
+            }
+            if arg == "--version" || arg == "-V" {
+                parsed.version = true;
+                continue;
+            }
+            let prefixed = arg.strip_prefix(PREFIX);
+            let Some(option) = prefixed.or_else(|| arg.strip_prefix("--")) else {
+                parsed.cargo.push(arg);
+                continue;
+            };
+            let (name, inline_value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (option, None),
+            };
+            if let Some(flag) = name.strip_prefix("no-") {
+                if let Some(&(flag, false)) = OWN_OPTIONS.iter().find(|(n, _)| *n == flag) {
+                    parsed.negated.push(flag);
+                    continue;
+                }
+            }
+            let Some(&(name, takes_value)) = OWN_OPTIONS.iter().find(|(n, _)| *n == name) else {
+                if prefixed.is_some() {
+                    return Err(format!("unknown option `{arg}`"));
+                }
+                // an option of `cargo test`
+                parsed.cargo.push(arg);
+                continue;
+            };
+            let value = match (takes_value, inline_value) {
+                (true, Some(value)) => Some(value),
+                (true, None) => Some(
+                    args.next()
+                        .filter(|value| value != "--")
+                        .ok_or_else(|| format!("`{arg}` requires a value"))?,
+                ),
+                (false, Some(_)) => return Err(format!("`{name}` doesn't take a value")),
+                (false, None) => None,
+            };
+            parsed.own.push((name, value));
+        }
+        Ok(parsed)
+    }
+
+    /// Whether the option is given.
+    pub fn given(&self, name: &str) -> bool {
+        self.own.iter().any(|(n, _)| *n == name)
+    }
+
+    /// Whether the flag is given and not turned off.
+    pub fn flag(&self, name: &str) -> bool {
+        self.given(name) && !self.negated.contains(&name)
+    }
+
+    /// The value of the option. If it's given multiple times, the last one wins.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.own
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// Values of the option forwarded to `cargo test`, like `target`.
+    pub fn cargo_values(&self, name: &str) -> Vec<&str> {
+        cargo_values(&self.cargo, name)
+    }
+
+    /// Add the options as defaults, which are overridden by the given ones with the same name
+    /// or in `overridden_by`, like `format` by `tui`.
+    pub fn add_defaults(&mut self, defaults: Args, overridden_by: &[(&str, &str)]) {
+        let mut own: Vec<_> = defaults
+            .own
+            .into_iter()
+            .filter(|&(name, _)| {
+                !self.given(name)
+                    && !self.negated.contains(&name)
+                    && !overridden_by
+                        .iter()
+                        .any(|&(n, by)| n == name && self.given(by))
+            })
+            .collect();
+        own.append(&mut self.own);
+        self.own = own;
+    }
+
+    /// Arguments to `cargo test`, followed by `--` and the ones to test binaries if any.
+    pub fn forward(&self) -> Vec<String> {
+        let mut forward = self.cargo.clone();
+        if !self.test_bins.is_empty() {
+            forward.push("--".into());
+            forward.extend(self.test_bins.iter().cloned());
+        }
+        forward
+    }
+}
@@ -0,0 +1,164 @@
+//! Defaults of the command line options from configuration files.
+//!
+//! They are read from the following places, where former ones take precedence, and the
+//! package directory is the current one or the one of `--manifest-path`:
+//!
+//! 1. `.pretty-test.toml` in the package directory or its closest ancestor with one
+//! 2. `[package.metadata.pretty-test]` in `Cargo.toml` of the package
+//! 3. `[workspace.metadata.pretty-test]` in `Cargo.toml` of the workspace root
+//!
+//! Keys are the long options without the leading `--`, like `collapse-passing = true`,
+//! `failure-lines = 10` or `only = ["failed", "ignored"]`. An option on the command line
+//! overrides the configured one, and `--no-<flag>` turns off a configured flag.
+
+use crate::{
+    args::{takes_value, Args},
+    rerun::Metadata,
+};
+use serde_json::{Map, Value};
+use std::{
+    io::{self, Error},
+    path::Path,
+};
+
+pub const CONFIG_FILE: &str = ".pretty-test.toml";
+
+/// The table under `package.metadata` and `workspace.metadata` in `Cargo.toml`.
+const METADATA_KEY: &str = "pretty-test";
+
+/// Options that can be configured, which excludes ones only making sense for a single run
+/// like `rerun-failed`.
+const KEYS: &[&str] = &[
+    "format",
+    "junit",
+    "libtest-json",
+    "stream",
+    "inline-failures",
+    "failure-lines",
+    "only",
+    "hide",
+    "collapse-passing",
+    "module-counts",
+    "sort",
+    "slowest",
+    "retries",
+    "fail-on-flaky",
+    "icons",
+];
+
+/// Options on the command line that also override a configured key.
+const OVERRIDDEN_BY: &[(&str, &str)] = &[("format", "tui")];
+
+/// Load the configuration for the package directory, and apply it to the arguments.
+///
+/// `cargo metadata` is only run when `cargo test` is, i.e. not for `--from-file`.
+/// Missing files and failures of `cargo metadata` are ignored.
+pub fn apply(args: &mut Args) -> io::Result<()> {
+    let cwd = std::env::current_dir()?;
+    let manifest = args
+        .cargo_values("manifest-path")
+        .pop()
+        .map(|path| cwd.join(path));
+    let dir = manifest
+        .as_deref()
+        .and_then(Path::parent)
+        .unwrap_or(&cwd)
+        .to_owned();
+    let meta = if args.given("from-file") {
+        None
+    } else {
+        Metadata::load(&args.cargo).ok()
+    };
+    let mut tables = Vec::with_capacity(3);
+    if let Some(meta) = &meta {
+        let pkg = meta.packages.iter().find(|pkg| {
+            Path::new(&pkg.manifest_path)
+                .parent()
+                .is_some_and(|pkg_dir| same_dir(pkg_dir, &dir))
+        });
+        let metadata = [
+            meta.workspace_metadata.as_ref(),
+            pkg.and_then(|pkg| pkg.metadata.as_ref()),
+        ];
+        tables.extend(
+            metadata
+                .into_iter()
+                .filter_map(|m| m?.get(METADATA_KEY).cloned()),
+        );
+    }
+    if let Some(path) = dir
+        .ancestors()
+        .map(|d| d.join(CONFIG_FILE))
+        .find(|p| p.is_file())
+    {
+        let text = std::fs::read_to_string(&path)?;
+        let table =
+            parse_toml(&text).map_err(|err| Error::other(format!("{}: {err}", path.display())))?;
+        tables.push(table);
+    }
+    let configured = to_args(&merge(&tables)).and_then(Args::parse);
+    args.add_defaults(configured.map_err(Error::other)?, OVERRIDDEN_BY);
+    Ok(())
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Merge tables of options, where latter ones take precedence.
+pub fn merge(tables: &[Value]) -> Value {
+    let mut merged = Map::new();
+    for table in tables {
+        if let Value::Object(pairs) = table {
+            for (key, value) in pairs {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    Value::Object(merged)
+}
+
+/// Turn a table of options into command line arguments, like `["--slowest", "10"]`.
+pub fn to_args(table: &Value) -> Result<Vec<String>, String> {
+    let Value::Object(pairs) = table else {
+        return Err("the configuration should be a table".into());
+    };
+    let mut args = Vec::new();
+    for (key, value) in pairs {
+        let Some(takes) = takes_value(key).filter(|_| KEYS.contains(&key.as_str())) else {
+            return Err(format!("unknown key `{key}` in the configuration"));
+        };
+        let flag = format!("--{key}");
+        let value = match (takes, value) {
+            (false, Value::Bool(true)) => {
+                args.push(flag);
+                continue;
+            }
+            (false, Value::Bool(false)) => continue,
+            (true, Value::String(s)) => s.clone(),
+            (true, Value::Number(n)) => format!("{n}"),
+            (true, Value::Array(items)) => items
+                .iter()
+                .map(|item| item.as_str().ok_or(()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|()| format!("`{key}` should be an array of strings"))?
+                .join(","),
+            (false, _) => return Err(format!("`{key}` should be true or false")),
+            (true, _) => {
+                return Err(format!(
+                    "`{key}` should be a string, a number or an array of strings"
+                ))
+            }
+        };
+        args.extend([flag, value]);
+    }
+    Ok(args)
+}
+
+/// Parse the configuration file, whose keys are in the order written.
+pub fn parse_toml(text: &str) -> Result<Value, String> {
+    toml::from_str(text).map_err(|err| err.to_string().trim_end().to_owned())
+}
@@ -0,0 +1,120 @@
+//! Detect compilation failures from stderr of `cargo test`.
+//!
+//! ```text
+//!    Compiling integration v0.1.0 (/root/cargo-pretty-test/tests/integration)
+//! error[E0425]: cannot find value `x` in this scope
+//!  --> src/lib.rs:1:21
+//!   |
+//! 1 | pub fn f() -> u32 { x }
+//!   |                     ^ not found in this scope
+//!
+//! error: could not compile `integration` (lib test) due to 1 previous error
+//! ```
+
+use crate::{failure::Location, parsing::Text, prettify::TestTree, regex::re};
+use colored::Colorize;
+use termtree::Tree;
+
+/// Compiler errors that stopped `cargo test` before running any test.
+#[derive(Debug, Default)]
+pub struct BuildFailure<'s> {
+    /// Crates that failed to compile in the order of `could not compile` lines.
+    pub crates: Vec<Crate<'s>>,
+    /// Errors not followed by a `could not compile` line.
+    pub others: Vec<Diagnostic<'s>>,
+}
+
+/// A crate that failed to compile.
+#[derive(Debug)]
+pub struct Crate<'s> {
+    pub name: Text<'s>,
+    /// The kind of target like `lib test` or `test "parsing"`, which is printed since Rust 1.72.
+    pub target: Option<Text<'s>>,
+    pub errors: Vec<Diagnostic<'s>>,
+}
+
+/// An error emitted by the compiler.
+#[derive(Debug)]
+pub struct Diagnostic<'s> {
+    /// The error code like `E0425` if any.
+    pub code: Option<Text<'s>>,
+    pub message: Text<'s>,
+    /// Where the error is from the ` --> src/lib.rs:1:21` line if any.
+    pub location: Option<Location<'s>>,
+}
+
+/// Parse compiler errors from stderr. None means the build didn't fail, i.e. there's no
+/// `error: could not compile` line, like for doc tests failing to compile, which are
+/// reported as failed tests.
+pub fn parse_build_failure(stderr: &str) -> Option<BuildFailure<'_>> {
+    let mut failure = BuildFailure::default();
+    let mut lines = stderr.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some(cap) = re().compile_error.captures(line) else {
+            continue;
+        };
+        let code = cap.name("code").map(|m| m.as_str());
+        let message = cap.name("message").map_or("", |m| m.as_str());
+        if let Some(krate) = cap.name("krate") {
+            // the errors above belong to the crate
+            failure.crates.push(Crate {
+                name: krate.as_str(),
+                target: cap.name("target").map(|m| m.as_str()),
+                errors: std::mem::take(&mut failure.others),
+            });
+        } else if code.is_some() || !is_cargo_error(message) {
+            let location = lines
+                .peek()
+                .and_then(|next| next.trim_start().strip_prefix("--> "))
+                .and_then(Location::parse);
+            failure.others.push(Diagnostic {
+                code,
+                message,
+                location,
+            });
+        }
+    }
+    (!failure.crates.is_empty()).then_some(failure)
+}
+
+/// Errors reported by cargo itself rather than the compiler.
+fn is_cargo_error(message: &str) -> bool {
+    ["test failed", "aborting due to"]
+        .iter()
+        .any(|prefix| message.starts_with(prefix))
+        || message.contains("targets failed")
+}
+
+impl<'s> BuildFailure<'s> {
+    /// Make the "Build failed" section with errors grouped by crates.
+    pub fn make_tree(&self) -> TestTree<'s> {
+        let mut tree = Tree::new("Build failed".red().bold().to_string().into());
+        for krate in &self.crates {
+            let root = match krate.target {
+                Some(target) => format!("{} ({target})", krate.name.blue().bold()),
+                None => krate.name.blue().bold().to_string(),
+            };
+            tree.push(
+                Tree::new(root.into()).with_leaves(krate.errors.iter().map(Diagnostic::make_tree)),
+            );
+        }
+        tree.extend(self.others.iter().map(Diagnostic::make_tree));
+        tree
+    }
+}
+
+impl<'s> Diagnostic<'s> {
+    fn make_tree(&self) -> TestTree<'s> {
+        let head = match self.code {
+            Some(code) => format!("error[{code}]: {}", self.message),
+            None => format!("error: {}", self.message),
+        };
+        let mut tree = Tree::new(head.red().to_string().into());
+        if let Some(location) = self.location {
+            tree.push(Tree::new(
+                format!("at {location}").bright_black().to_string().into(),
+            ));
+        }
+        tree
+    }
+}
@@ -0,0 +1,81 @@
+//! Errors from parsing the output of `cargo test`.
+//!
+//! Offsets are in bytes from the start of the parsed text (stderr or stdout),
+//! and lines are the offending ones without the line break.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The amount of test runners from stderr (`Running ...`/`Doc-tests ...` lines)
+    /// differs from that from stdout (`running N tests` lines).
+    RunnerCountMismatch { stderr: usize, stdout: usize },
+    /// No `Running ...` or `Doc-tests ...` line in stderr.
+    NoTestRunner,
+    /// No `running N tests` line in stdout.
+    NoTestRunning,
+    /// A test runner starting at the `running N tests` line has no `test result: ...` line.
+    MissingStats { offset: usize, line: String },
+    /// The `test result: ...` line contains an invalid number.
+    InvalidStats {
+        offset: usize,
+        line: String,
+        reason: String,
+    },
+    /// The source path of a test runner is not in `src/`, `tests/`, `examples/` or `benches/`.
+    UnknownTestType { offset: usize, line: String },
+    /// The path to a test binary is not like `<target-dir>/[<triple>/]<profile>/deps/<name>-<hash>`.
+    InvalidBinPath { offset: usize, line: String },
+    /// The amount in `running N tests` differs from the amount of parsed test lines.
+    TotalMismatch {
+        offset: usize,
+        line: String,
+        head: u32,
+        parsed: u32,
+    },
+    /// Failed to serialize the parsed result into JSON.
+    JsonOutput(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::RunnerCountMismatch { stderr, stdout } => write!(
+                f,
+                "found {stderr} test runners in stderr, but {stdout} in stdout"
+            ),
+            Error::NoTestRunner => f.write_str("no `Running ...` or `Doc-tests ...` in stderr"),
+            Error::NoTestRunning => f.write_str("no `running N tests` in stdout"),
+            Error::MissingStats { offset, line } => write!(
+                f,
+                "no `test result: ...` for `{line}` at byte {offset} of stdout"
+            ),
+            Error::InvalidStats {
+                offset,
+                line,
+                reason,
+            } => write!(f, "invalid `{line}` at byte {offset} of stdout: {reason}"),
+            Error::UnknownTestType { offset, line } => write!(
+                f,
+                "unknown type of test runner `{line}` at byte {offset} of stderr"
+            ),
+            Error::InvalidBinPath { offset, line } => write!(
+                f,
+                "failed to parse the path to the test binary in `{line}` at byte {offset} of stderr"
+            ),
+            Error::TotalMismatch {
+                offset,
+                line,
+                head,
+                parsed,
+            } => write!(
+                f,
+                "`{line}` at byte {offset} of stdout expects {head} tests, but {parsed} are found"
+            ),
+            Error::JsonOutput(reason) => write!(f, "failed to output JSON: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
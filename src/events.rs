@@ -0,0 +1,190 @@
+//! Support libtest's structured JSON event stream.
+//!
+//! `cargo test -- -Z unstable-options --format json --report-time` makes test binaries
+//! emit one JSON object per line instead of human-oriented texts:
+//!
+//! ```text
+//! { "type": "suite", "event": "started", "test_count": 2 }
+//! { "type": "test", "event": "started", "name": "submod::normal_test" }
+//! { "type": "test", "name": "submod::normal_test", "event": "ok", "exec_time": 0.000000888 }
+//! { "type": "test", "name": "submod::ignore", "event": "ignored", "message": "reason" }
+//! { "type": "suite", "event": "ok", "passed": 1, "failed": 0, "ignored": 1, "measured": 0, "filtered_out": 0, "exec_time": 0.017 }
+//! ```
+//!
+//! The events are rendered back into the canonical text from libtest with exact
+//! per-test timings (e.g. `test submod::normal_test ... ok <0.000000888s>`) and captured
+//! stdout in the failures section, so the same parser builds the same model for both.
+//!
+//! Passed tests come with their stdout in the events for `--show-output`, while lines printed
+//! with `--nocapture` are between the `started` event of the test and its result. Both are
+//! rendered in the successes section like `--show-output` does for texts.
+
+use serde_json::Value;
+use std::{fmt::Write, process::Command};
+
+/// Arguments for test binaries to emit JSON events.
+pub const LIBTEST_JSON_ARGS: [&str; 5] = [
+    "-Z",
+    "unstable-options",
+    "--format",
+    "json",
+    "--report-time",
+];
+
+/// Check if the toolchain accepts unstable libtest options, i.e. it's a nightly
+/// toolchain or `RUSTC_BOOTSTRAP` is set.
+pub fn toolchain_supports_json() -> bool {
+    if std::env::var_os("RUSTC_BOOTSTRAP").is_some_and(|v| !v.is_empty() && v != "0") {
+        return true;
+    }
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    Command::new(rustc)
+        .arg("--version")
+        .output()
+        .is_ok_and(|out| {
+            let version = String::from_utf8_lossy(&out.stdout);
+            version.contains("-nightly") || version.contains("-dev")
+        })
+}
+
+/// Insert the arguments for JSON events to the ones forwarded to `cargo test`,
+/// i.e. after `--` which will be added if absent.
+pub fn add_libtest_json_args(args: &mut Vec<String>) {
+    let pos = if let Some(pos) = args.iter().position(|arg| arg == "--") {
+        pos + 1
+    } else {
+        args.push("--".into());
+        args.len()
+    };
+    args.splice(pos..pos, LIBTEST_JSON_ARGS.map(String::from));
+}
+
+/// A test runner being rendered.
+#[derive(Default)]
+struct Suite {
+    tree: String,
+    successes: String,
+    failures: String,
+    failed_names: Vec<String>,
+    /// Tests started but not finished yet.
+    running: Vec<String>,
+    /// Lines printed by the only running test with `--nocapture`.
+    printed: String,
+}
+
+impl Suite {
+    /// Take the output of the finished test, printed or in the event.
+    fn output(&mut self, name: &str, event: &Value) -> String {
+        self.running.retain(|running| running != name);
+        let mut output = std::mem::take(&mut self.printed);
+        output.push_str(
+            event
+                .get("stdout")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        );
+        output
+    }
+}
+
+/// Render the JSON events from stdout of `cargo test` into canonical texts from libtest.
+///
+/// Lines that are not JSON events (like printing from tests with `--nocapture`) belong to
+/// the running test, or are dropped if multiple tests are running.
+pub fn events_to_text(stdout: &str) -> String {
+    let mut text = String::with_capacity(stdout.len());
+    let mut suite = Suite::default();
+    for line in stdout.lines() {
+        let event = Some(line)
+            .filter(|line| line.trim_start().starts_with('{'))
+            .and_then(|line| serde_json::from_str::<Value>(line).ok());
+        let Some(event) = event else {
+            if suite.running.len() == 1 {
+                suite.printed.push_str(line);
+                suite.printed.push('\n');
+            }
+            continue;
+        };
+        let field = |key| event.get(key).and_then(Value::as_str).unwrap_or_default();
+        let number = |key| event.get(key).and_then(Value::as_f64).unwrap_or_default();
+        match (field("type"), field("event")) {
+            ("suite", "started") => {
+                suite = Suite::default();
+                let amount = number("test_count");
+                let s = if (amount - 1.0).abs() < f64::EPSILON {
+                    ""
+                } else {
+                    "s"
+                };
+                _ = writeln!(text, "\nrunning {amount} test{s}");
+            }
+            ("suite", status @ ("ok" | "failed")) => {
+                text.push_str(&suite.tree);
+                if !suite.successes.is_empty() {
+                    _ = write!(text, "\nsuccesses:\n{}\nsuccesses:\n", suite.successes);
+                }
+                if !suite.failures.is_empty() {
+                    _ = write!(text, "\nfailures:\n{}\nfailures:\n", suite.failures);
+                    for name in &suite.failed_names {
+                        _ = writeln!(text, "    {name}");
+                    }
+                }
+                _ = writeln!(
+                    text,
+                    "\ntest result: {}. {} passed; {} failed; {} ignored; {} measured; \
+                     {} filtered out; finished in {}s\n",
+                    if status == "ok" { "ok" } else { "FAILED" },
+                    number("passed"),
+                    number("failed"),
+                    number("ignored"),
+                    number("measured"),
+                    number("filtered_out"),
+                    number("exec_time"),
+                );
+            }
+            ("test", "started") => suite.running.push(field("name").to_owned()),
+            ("test", "ok") => {
+                let name = field("name");
+                _ = writeln!(suite.tree, "test {name} ... ok{}", time(&event));
+                let output = suite.output(name, &event);
+                if !output.trim().is_empty() {
+                    _ = writeln!(suite.successes, "\n---- {name} stdout ----\n{output}");
+                }
+            }
+            ("test", "ignored") => {
+                suite.running.retain(|running| running != field("name"));
+                let reason = event.get("message").and_then(Value::as_str);
+                _ = writeln!(
+                    suite.tree,
+                    "test {} ... ignored{}",
+                    field("name"),
+                    reason.map(|r| format!(", {r}")).unwrap_or_default()
+                );
+            }
+            ("test", "failed") => {
+                let name = field("name");
+                _ = writeln!(suite.tree, "test {name} ... FAILED{}", time(&event));
+                _ = write!(suite.failures, "\n---- {name} stdout ----\n");
+                let output = suite.output(name, &event);
+                if !output.is_empty() {
+                    _ = writeln!(suite.failures, "{}", output.trim_end());
+                }
+                if let Some(message) = event.get("message").and_then(Value::as_str) {
+                    _ = writeln!(suite.failures, "note: {message}");
+                }
+                suite.failed_names.push(name.to_owned());
+            }
+            _ => (),
+        }
+    }
+    text
+}
+
+/// ` <0.123s>` suffix reported by `--report-time`.
+fn time(event: &Value) -> String {
+    event
+        .get("exec_time")
+        .and_then(Value::as_f64)
+        .map(|t| format!(" <{t}s>"))
+        .unwrap_or_default()
+}
@@ -0,0 +1,180 @@
+//! Structured information about failed tests extracted from the failures section.
+//!
+//! ```text
+//! failures:
+//!
+//! ---- submod::panic::panicked stdout ----
+//! thread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:
+//! explicit panic
+//! note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+//!
+//! ---- submod::assert stdout ----
+//! thread 'submod::assert' panicked at src/lib.rs:20:9:
+//! assertion `left == right` failed
+//!   left: 1
+//!  right: 2
+//!
+//! failures:
+//!     submod::panic::panicked
+//!     submod::assert
+//! ```
+
+use crate::{parsing::Text, regex::re};
+
+/// A failed test parsed from its `---- name stdout ----` block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Failure<'s> {
+    /// Test name in the header.
+    pub name: Text<'s>,
+    /// The whole block without the header, i.e. captured stdout and the panic info.
+    pub stdout: Text<'s>,
+    /// Name of the thread that panicked, which is usually the test name.
+    pub thread: Option<Text<'s>>,
+    /// The panic message which may span multiple lines, or the first line
+    /// of the block if it's not a panic, like `test did not panic as expected`.
+    pub message: Text<'s>,
+    /// Where the panic occurred.
+    pub location: Option<Location<'s>>,
+    /// Left value from `assert_eq!` or `assert_ne!`.
+    pub left: Option<Text<'s>>,
+    /// Right value from `assert_eq!` or `assert_ne!`.
+    pub right: Option<Text<'s>>,
+}
+
+/// Source location of a panic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Location<'s> {
+    pub file: Text<'s>,
+    pub line: u32,
+    pub col: u32,
+}
+
+impl Location<'_> {
+    /// Parse `file:line:col`.
+    pub fn parse(s: &str) -> Option<Location<'_>> {
+        let mut split = s.trim().rsplitn(3, ':');
+        let col = split.next()?.parse().ok()?;
+        let line = split.next()?.parse().ok()?;
+        let file = split.next()?;
+        Some(Location { file, line, col })
+    }
+}
+
+impl std::fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
+    }
+}
+
+/// Split the detail of a test runner into `(name, block)` for each `---- name stdout ----`
+/// block in the section started by the `section` line, like `failures:` or `successes:`.
+pub fn stdout_blocks<'s>(detail: Text<'s>, section: &str) -> Vec<(Text<'s>, Text<'s>)> {
+    let mut lines = detail.split_inclusive('\n');
+    let mut pos = 0;
+    // skip to the start of the section
+    for line in lines.by_ref() {
+        pos += line.len();
+        if line.trim_end() == section {
+            break;
+        }
+    }
+    let mut blocks: Vec<(Text, usize, usize)> = Vec::new();
+    for line in lines {
+        let start = pos;
+        pos += line.len();
+        let trimmed = line.trim_end();
+        if let Some(cap) = re().stdout_header.captures(trimmed) {
+            if let Some(last) = blocks.last_mut() {
+                last.2 = start;
+            }
+            let name = cap.name("name").map_or("", |m| m.as_str());
+            blocks.push((name, pos, detail.len()));
+        } else if trimmed == section {
+            // the list of test names after the blocks
+            if let Some(last) = blocks.last_mut() {
+                last.2 = start;
+            }
+            break;
+        }
+    }
+    blocks
+        .into_iter()
+        .map(|(name, start, end)| (name, detail[start.min(end)..end].trim()))
+        .collect()
+}
+
+/// Parse the failures section in the detail of a test runner.
+pub fn parse_failures(detail: Text<'_>) -> Vec<Failure<'_>> {
+    stdout_blocks(detail, "failures:")
+        .into_iter()
+        .map(|(name, stdout)| Failure::parse(name, stdout))
+        .collect()
+}
+
+impl<'s> Failure<'s> {
+    /// Parse the block of a failed test without the header.
+    pub fn parse(name: Text<'s>, stdout: Text<'s>) -> Failure<'s> {
+        let mut failure = Failure {
+            name,
+            stdout,
+            thread: None,
+            message: stdout.lines().find(|l| !l.trim().is_empty()).unwrap_or(""),
+            location: None,
+            left: None,
+            right: None,
+        };
+        if let Some(cap) = re().panic.captures(stdout) {
+            failure.thread = cap.name("thread").map(|m| m.as_str());
+            if let Some(msg) = cap.name("old_msg") {
+                // before Rust 1.73: panicked at 'msg', src/lib.rs:11:13
+                failure.message = msg.as_str();
+                failure.location = cap
+                    .name("old_loc")
+                    .and_then(|m| Location::parse(m.as_str()));
+            } else if let Some(loc) = cap.name("loc") {
+                // since Rust 1.73: panicked at src/lib.rs:11:13:\nmsg
+                failure.location = Location::parse(loc.as_str());
+                let rest = &stdout[cap.get(0).map_or(stdout.len(), |m| m.end())..];
+                failure.message = panic_message(rest);
+            }
+        } else if let Some(note) = failure.message.strip_prefix("note: ") {
+            // note: test did not panic as expected at src/lib.rs:22:12
+            failure.message = note;
+            if let Some((msg, loc)) = note.rsplit_once(" at ") {
+                if let Some(loc) = Location::parse(loc) {
+                    failure.message = msg;
+                    failure.location = Some(loc);
+                }
+            }
+        }
+        if let Some(cap) = re().assert_values.captures(failure.message) {
+            let value = |name| {
+                cap.name(name).map(|m| {
+                    // before Rust 1.73: left: `1`,
+                    let v = m.as_str().trim_end();
+                    let v = v.strip_suffix(',').unwrap_or(v);
+                    v.strip_prefix('`')
+                        .and_then(|v| v.strip_suffix('`'))
+                        .unwrap_or(v)
+                })
+            };
+            failure.left = value("left");
+            failure.right = value("right");
+        }
+        failure
+    }
+}
+
+/// The panic message after `panicked at file:line:col:`, which ends before
+/// the backtrace or the note about it.
+fn panic_message(rest: &str) -> &str {
+    let rest = rest.strip_prefix('\n').unwrap_or(rest);
+    let end = ["\nnote: ", "\nstack backtrace:"]
+        .iter()
+        .filter_map(|pat| rest.find(pat))
+        .min()
+        .unwrap_or(rest.len());
+    rest[..end].trim_end()
+}
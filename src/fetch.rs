@@ -1,26 +1,228 @@
 use crate::{
-    parsing::{parse_cargo_test, Stats},
-    prettify::{make_pretty, TestTree, ICON_NOTATION},
+    args::Args,
+    config,
+    diagnostic::parse_build_failure,
+    events::{add_libtest_json_args, events_to_text, toolchain_supports_json},
+    history::{compare, load_run, save_run, Diff},
+    junit::junit_report,
+    markdown::{markdown_build_failure, markdown_report},
+    output::attribute_nocapture,
+    parsing::{parse_cargo_test, split_combined_output, Data, Stats, TestRunners},
+    prettify::{format_duration, runner_tree, Options, Sort, Status, StatusFilter, TestTree},
     regex::re,
+    rerun::{
+        failed_tests, filter_args, load_failed, rerun_args, save_failed, target_dir,
+        tests_with_status, FailedTest, Metadata,
+    },
+    stream::{spawn, LivePrinter},
+    theme::{set_theme, theme, Theme},
+    watch::{Watcher, WATCHED_DIRS},
     Result,
 };
 use colored::{control::set_override, Colorize};
-use std::process::{Command, ExitCode, Output};
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode, ExitStatus, Output},
+};
 use termtree::Tree;
 
-/// Output from `cargo test`
+pub const USAGE: &str = "
+Usage: cargo pretty-test [OPTIONS] [CARGO TEST ARGS] [-- [TEST BINARY ARGS]...]
+
+Arguments other than the options below are forwarded to `cargo test`, and those after `--`
+to test binaries.
+";
+
+/// Options only recognized by cargo-pretty-test, i.e. not forwarded to `cargo test`.
+pub const OPTIONS: &str = "
+Options (also accepted with the `pretty-` prefix, like `--pretty-format json`, which never
+clashes with options of `cargo test`):
+    --format <FORMAT>    Output format: pretty (default), json (with the `serde` feature),
+                         markdown or tui; the markdown report is also appended to
+                         $GITHUB_STEP_SUMMARY if set
+    --tui                Browse the test tree interactively, same as `--format tui`
+    --junit <PATH>       Write a JUnit XML report to PATH besides the test tree
+    --from-file <PATH>   Parse a saved log of `cargo test` with stderr and stdout combined
+                         instead of running it; use `-` to read from stdin
+    --stream             Print each test runner as soon as it finishes instead of
+                         the whole tree at the end
+    --libtest-json       Read libtest's JSON events for exact per-test timings (requires
+                         a nightly toolchain or RUSTC_BOOTSTRAP, otherwise ignored)
+    --inline-failures    Show the panic message and location under each failed test
+                         instead of printing the failures section above the tree
+    --failure-lines <N>  Max lines of the panic message for --inline-failures, or of the
+                         output from `-- --show-output` or `-- --nocapture` under each passed
+                         test (default: 5, 0 means no limit)
+    --rerun-failed       Only run the tests that failed in the last run
+    --retries <N>        Rerun failed tests up to N times, and mark those passing on retry
+                         as flaky instead of failed
+    --fail-on-flaky      Fail the run if any test is flaky (only works with --retries)
+    --watch              Rerun tests when files in src, tests, examples or benches of the
+                         workspace change, and highlight the changes since the previous run
+    --compare <RUN>      Mark tests that are newly failing, newly passing, new or significantly
+                         slower since a past run, where RUN is a run id in
+                         target/pretty-test/history or `last` for the previous run
+    --only <STATUSES>    Only show tests in the statuses in the tree, like `failed,ignored`,
+                         where statuses are passed, failed, ignored and flaky; the summary
+                         still counts all the tests
+    --hide <STATUSES>    Hide tests in the statuses from the tree, like `passed`
+    --collapse-passing   Show each module or runner whose tests all passed as a single line
+    --module-counts      Show the numbers of tests in each status beside modules
+    --sort <ORDER>       Order of tests and modules under each module: name (default),
+                         status for failed ones first, duration for slowest ones first, or
+                         declaration for the order reported by libtest
+    --slowest <N>        List the N slowest tests after the summary (requires per-test
+                         durations from --libtest-json or `-- -Z unstable-options --report-time`)
+    --icons <THEME>      Icons of test statuses: emoji (default), ascii like [PASS], or
+                         nerdfont; icons can also be set one by one after a theme, like
+                         `ascii,flaky=[RETRY]`, where statuses are passed, failed, flaky,
+                         ignored and filtered
+
+Defaults of these options can be set with the keys without `--`, like `slowest = 10`, in
+`.pretty-test.toml`, `[package.metadata.pretty-test]` or `[workspace.metadata.pretty-test]`
+of Cargo.toml. Options on the command line override them, and `--no-<flag>` turns off a flag.
+";
+
+/// Output format of the parsed result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// The test tree and summary.
+    #[default]
+    Pretty,
+    /// The whole parsed result in JSON, only available with the `serde` feature.
+    #[cfg(feature = "serde")]
+    Json,
+    /// A report in Markdown, like for GitHub step summaries.
+    Markdown,
+    /// The interactive test tree, which requires the `tui` feature.
+    Tui,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Format::Pretty),
+            #[cfg(feature = "serde")]
+            "json" => Ok(Format::Json),
+            #[cfg(not(feature = "serde"))]
+            "json" => Err(
+                "format `json` requires cargo-pretty-test built with the `serde` feature".into(),
+            ),
+            "markdown" => Ok(Format::Markdown),
+            "tui" => Ok(Format::Tui),
+            _ => Err(format!(
+                "unknown format `{s}`, expected one of pretty, json, markdown, tui"
+            )),
+        }
+    }
+}
+
+/// Run `cargo test` and emit the output.
+#[allow(clippy::struct_excessive_bools)]
 pub struct Emit {
-    /// Raw output. None means don't run `cargo test` like for `--version`.
-    output: Option<Output>,
-    /// Don't parse the output. Forward the output instead.
-    no_parse: bool,
+    /// The `cargo test` commands whose outputs are merged, which is one command
+    /// except for `--rerun-failed`. Empty means don't run it like for `--version`.
+    cmds: Vec<Command>,
+    /// Build `cmds` from the tests failed in the last run before each run.
+    rerun_failed: bool,
+    /// Print the help of cargo-pretty-test and `cargo test` instead of running tests.
+    help: bool,
+    /// Path to write the JUnit XML report to.
+    junit: Option<PathBuf>,
+    /// Path to append the Markdown report to, which is `$GITHUB_STEP_SUMMARY` if set.
+    step_summary: Option<PathBuf>,
+    format: Format,
+    /// Stdout consists of JSON events from libtest rather than texts.
+    libtest_json: bool,
+    /// Print each test runner as soon as it finishes.
+    stream: bool,
+    /// Read the output from a saved log instead of running `cargo test`.
+    /// `-` means stdin.
+    from_file: Option<String>,
+    /// How to render the test tree.
+    tree: Options,
+    /// Number of the slowest tests to list after the summary.
+    slowest: Option<usize>,
+    /// Rerun failed tests to detect flaky ones.
+    retry: Option<Retry>,
+    /// The id of a past run to compare with, or `last`.
+    compare: Option<String>,
+    /// Rerun when sources change.
+    watch: Option<Watcher>,
+    /// Target triples given by `--target` or `CARGO_BUILD_TARGET`.
+    targets: Vec<String>,
+    /// Arguments to `cargo test`, to which target selectors and test filters are added
+    /// when rerunning some of the tests.
+    forward: Vec<String>,
+}
+
+/// Rerun failed tests for `--retries`.
+struct Retry {
+    /// Max times to rerun a failed test.
+    times: usize,
+    /// Fail the run if any test only passes on retry.
+    fail_on_flaky: bool,
 }
 
 impl Emit {
-    pub fn run(self) -> ExitCode {
-        let Emit { output, no_parse } = self;
-        let Some(output) = output else {
+    pub fn run(mut self) -> ExitCode {
+        let Some(mut watcher) = self.watch.take() else {
+            return self.run_once();
+        };
+        loop {
+            self.run_once();
+            // files written by the tests shouldn't trigger another run
+            watcher.reset();
+            println!(
+                "\n{}",
+                "Waiting for changes... (press Ctrl-C to quit)".bright_black()
+            );
+            watcher.wait();
+            // clear the screen and move the cursor to the top left
+            print!("\x1B[2J\x1B[H");
+            // highlight the delta from the previous run
+            self.compare.get_or_insert_with(|| "last".into());
+        }
+    }
+
+    fn run_once(&mut self) -> ExitCode {
+        if self.help {
+            print_help();
             return ExitCode::SUCCESS;
+        }
+        let stream = self.stream && self.format == Format::Pretty;
+        let output = if let Some(path) = &self.from_file {
+            match read_log(path) {
+                Ok(output) => output,
+                Err(err) => {
+                    eprintln!("{} {path}: {err}", "Failed to read".red().bold());
+                    return ExitCode::FAILURE;
+                }
+            }
+        } else {
+            if self.rerun_failed {
+                match rerun_failed_commands(&self.forward) {
+                    Ok(cmds) => self.cmds = cmds,
+                    Err(err) => {
+                        eprintln!("{}", err.red().bold());
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            if self.cmds.is_empty() {
+                return ExitCode::SUCCESS;
+            }
+            // report it instead of panicking to keep watching
+            match self.run_cmds(stream) {
+                Ok(output) => output,
+                Err(err) => {
+                    eprintln!("{}: {err}", "Failed to run `cargo test`".red().bold());
+                    return ExitCode::FAILURE;
+                }
+            }
         };
         let raw_err = String::from_utf8_lossy(&output.stderr);
         let raw_out = String::from_utf8_lossy(&output.stdout);
@@ -28,33 +230,288 @@ impl Emit {
         let stdout = strip_ansi_escapes::strip(&*raw_out);
         let stderr = String::from_utf8_lossy(&stderr);
         let stdout = String::from_utf8_lossy(&stdout);
-        if no_parse {
-            println!(
-                "{phelp}\n{ICON_NOTATION}\n{sep}\n\n{help}",
-                phelp = "cargo pretty-test help:".blue().bold(),
-                sep = re().separator,
-                help = "cargo test help:".blue().bold()
-            );
-            eprintln!("{stderr}");
-            println!("{stdout}");
+        let stdout: String = if self.libtest_json {
+            events_to_text(&stdout)
         } else {
-            let (tree, stats) = match parse_cargo_test_output(&stderr, &stdout) {
-                Ok(res) => res,
-                Err(err) => {
-                    println!(
-                        "{}:\n{err}\n\n{}\n{raw_err}\n{raw_out}",
-                        "Error from cargo-pretty-test".red().bold(),
-                        "Error from cargo test:".red().bold()
+            attribute_nocapture(&stdout).into_owned()
+        };
+        if let Some(failure) = parse_build_failure(&stderr) {
+            if let Some(path) = &self.step_summary {
+                if let Err(err) = append(path, &markdown_build_failure(&failure)) {
+                    eprintln!(
+                        "{} {}: {err}",
+                        "Failed to write the step summary to".yellow(),
+                        path.display()
                     );
-                    return ExitCode::FAILURE;
                 }
+            }
+            let tree = failure.make_tree();
+            if self.format == Format::Pretty {
+                println!("{tree}");
+            } else {
+                eprintln!("{tree}");
+            }
+            // exit with cargo's own exit code, but a saved log has no exit code
+            let code = output
+                .status
+                .code()
+                .and_then(|code| u8::try_from(code).ok());
+            return match code {
+                Some(code) if code != 0 => ExitCode::from(code),
+                _ => ExitCode::FAILURE,
             };
-            println!("{tree}\n{stats}");
-            if !stats.ok {
+        }
+        match parse_cargo_test(&stderr, &stdout) {
+            Ok(mut runners) => {
+                runners.set_targets(&self.targets);
+                if let Some(retry) = &self.retry {
+                    retry.run(&mut runners, &self.forward, self.libtest_json);
+                }
+                let diff = self.compare.as_deref().and_then(|id| {
+                    let diff = target_dir(&self.forward)
+                        .and_then(|dir| load_run(&dir, id))
+                        .map(|base| compare(&mut runners, &base));
+                    diff.map_err(|err| {
+                        eprintln!("{}: {err}", "Failed to compare runs".red().bold());
+                    })
+                    .ok()
+                });
+                self.report(&runners, stream, diff.as_ref())
+            }
+            Err(err) => {
+                println!(
+                    "{}:\n{err}\n\n{}\n{raw_err}\n{raw_out}",
+                    "Error from cargo-pretty-test".red().bold(),
+                    "Error from cargo test:".red().bold()
+                );
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    /// Run the `cargo test` commands and merge their outputs.
+    fn run_cmds(&mut self, stream: bool) -> std::io::Result<Output> {
+        let mut printer = LivePrinter::new(self.libtest_json, self.tree);
+        let mut output = Output {
+            status: ExitStatus::default(),
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        };
+        for cmd in &mut self.cmds {
+            let out = spawn(cmd, |line| {
+                if stream {
+                    printer.feed(line);
+                }
+            })?;
+            // keep the first failure
+            if output.status.success() {
+                output.status = out.status;
+            }
+            output.stdout.extend(out.stdout);
+            output.stderr.extend(out.stderr);
+        }
+        Ok(output)
+    }
+
+    /// Write reports for the parsed result, and fail if any test fails.
+    ///
+    /// If test runners are streamed, they've been printed and only the summary is left.
+    fn report(&self, runners: &TestRunners, stream: bool, diff: Option<&Diff>) -> ExitCode {
+        // record the run for `--rerun-failed` and `--compare` unless it's from a saved log
+        if self.from_file.is_none() {
+            let saved = target_dir(&self.forward).and_then(|dir| {
+                save_failed(&dir, &failed_tests(runners))?;
+                save_run(&dir, runners)
+            });
+            if let Err(err) = saved {
+                eprintln!("{}: {err}", "Failed to record the run".yellow());
+            }
+        }
+        if let Some(path) = &self.junit {
+            if let Err(err) = std::fs::write(path, junit_report(runners)) {
+                eprintln!(
+                    "{} {}: {err}",
+                    "Failed to write the JUnit report to".red().bold(),
+                    path.display()
+                );
                 return ExitCode::FAILURE;
             }
         }
-        ExitCode::SUCCESS
+        if let Some(path) = &self.step_summary {
+            if let Err(err) = append(path, &markdown_report(runners, &self.tree)) {
+                eprintln!(
+                    "{} {}: {err}",
+                    "Failed to write the step summary to".yellow(),
+                    path.display()
+                );
+            }
+        }
+        let stats = match self.format {
+            Format::Pretty => {
+                let stats = if stream {
+                    let stats = runners.stats();
+                    println!("\n{stats}");
+                    stats
+                } else {
+                    let (tree, stats) = make_test_tree(runners, &self.tree);
+                    println!("{tree}\n{stats}");
+                    stats
+                };
+                if let Some(diff) = diff {
+                    println!("{diff}");
+                }
+                if let Some(n) = self.slowest {
+                    println!("\n{}", slowest_report(runners, n));
+                }
+                stats
+            }
+            #[cfg(feature = "serde")]
+            Format::Json => match json_report(runners) {
+                Ok(json) => {
+                    println!("{json}");
+                    runners.stats()
+                }
+                Err(err) => {
+                    eprintln!("{}: {err}", "Error from cargo-pretty-test".red().bold());
+                    return ExitCode::FAILURE;
+                }
+            },
+            Format::Markdown => {
+                println!("{}", markdown_report(runners, &self.tree));
+                runners.stats()
+            }
+            Format::Tui => {
+                if let Err(err) = browse(runners, &self.forward, self.libtest_json) {
+                    eprintln!("{}: {err}", "Error from cargo-pretty-test".red().bold());
+                    return ExitCode::FAILURE;
+                }
+                let stats = runners.stats();
+                println!("{stats}");
+                stats
+            }
+        };
+        let fail_on_flaky = self.retry.as_ref().is_some_and(|r| r.fail_on_flaky);
+        if fail_on_flaky && stats.flaky != 0 {
+            eprintln!(
+                "{}",
+                format!(
+                    "{} flaky tests fail the run due to --fail-on-flaky",
+                    stats.flaky
+                )
+                .red()
+                .bold()
+            );
+            return ExitCode::FAILURE;
+        }
+        if stats.ok {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+}
+
+impl Retry {
+    /// Rerun the failed tests until they pass or run out of retries, and mark
+    /// those passing on retry as flaky. Each failed test is rerun alone in a `cargo test`
+    /// process.
+    fn run(&self, runners: &mut TestRunners, forward: &[String], libtest_json: bool) {
+        let mut failed = failed_tests(runners);
+        if failed.is_empty() {
+            return;
+        }
+        let meta = match Metadata::load(forward) {
+            Ok(meta) => meta,
+            Err(err) => {
+                eprintln!("{}: {err}", "Failed to retry failed tests".yellow());
+                return;
+            }
+        };
+        let mut flaky: Vec<FailedTest> = Vec::new();
+        for attempt in 1..=self.times {
+            if failed.is_empty() {
+                break;
+            }
+            eprintln!(
+                "{}",
+                format!(
+                    "Retrying {} failed tests ({attempt}/{})",
+                    failed.len(),
+                    self.times
+                )
+                .yellow()
+                .bold()
+            );
+            let mut passed = Vec::new();
+            // each test runs in its own process, so that it isn't affected by the other
+            // failed tests, like through global state
+            for test in &failed {
+                let Some(args) = filter_args(&meta, test, Some(&test.name), true, forward) else {
+                    eprintln!(
+                        "can't find the target of `{}` in {}, skip it",
+                        test.name, test.src_path
+                    );
+                    continue;
+                };
+                let (stderr, stdout) = match cargo_test_output(&args, libtest_json) {
+                    Ok(output) => output,
+                    Err(err) => {
+                        eprintln!("{}: {err}", "Failed to retry failed tests".yellow());
+                        continue;
+                    }
+                };
+                match parse_cargo_test(&stderr, &stdout) {
+                    Ok(retried) => {
+                        if tests_with_status(&retried, Status::Ok).contains(test) {
+                            passed.push(test.clone());
+                        }
+                    }
+                    Err(err) => eprintln!("{}: {err}", "Failed to parse the retry".yellow()),
+                }
+            }
+            failed.retain(|test| {
+                let pass = passed.contains(test);
+                if pass {
+                    flaky.push(test.clone());
+                }
+                !pass
+            });
+        }
+        runners.mark_flaky(|runner, test| flaky.iter().any(|t| t.is(runner, test)));
+    }
+}
+
+/// Run `cargo test` with the arguments, and return its stderr and stdout without ANSI escapes,
+/// where JSON events from libtest are turned into texts and the output printed by tests
+/// is moved out of the test lines.
+pub fn cargo_test_output(args: &[String], libtest_json: bool) -> std::io::Result<(String, String)> {
+    let output = Command::new("cargo").arg("test").args(args).output()?;
+    let strip =
+        |bytes: &[u8]| String::from_utf8_lossy(&strip_ansi_escapes::strip(bytes)).into_owned();
+    let stdout = strip(&output.stdout);
+    let stdout = if libtest_json {
+        events_to_text(&stdout)
+    } else {
+        attribute_nocapture(&stdout).into_owned()
+    };
+    Ok((strip(&output.stderr), stdout))
+}
+
+/// Print the help of cargo-pretty-test, followed by the one of `cargo test` for forwarded
+/// arguments.
+fn print_help() {
+    println!(
+        "{}\n{USAGE}{OPTIONS}{}\n{}\n\n{}",
+        "cargo pretty-test help:".blue().bold(),
+        theme().notation(),
+        re().separator,
+        "cargo test help (for arguments forwarded to `cargo test`):"
+            .blue()
+            .bold()
+    );
+    match Command::new("cargo").args(["test", "--help"]).output() {
+        Ok(output) => print!("{}", String::from_utf8_lossy(&output.stdout)),
+        Err(err) => eprintln!("{}: {err}", "Failed to run `cargo test --help`".red()),
     }
 }
 
@@ -65,11 +522,11 @@ pub fn run() -> ExitCode {
 
 /// Collect arguments and forward them to `cargo test`.
 ///
-/// Note: This filters some arguments that mess up the output, like
-/// `--nocapture` which prints in the status part and hinders parsing.
+/// Note: `--nocapture` makes tests run one at a time unless `--test-threads` is given,
+/// so that the output printed by each test can be told apart.
 pub fn cargo_test() -> Emit {
     let passin: Vec<_> = std::env::args().collect();
-    let forward = if passin
+    let passin = if passin
         .get(..2)
         .is_some_and(|v| v[0].ends_with("cargo-pretty-test") && v[1] == "pretty-test")
     {
@@ -79,29 +536,304 @@ pub fn cargo_test() -> Emit {
         // `cargo-pretty-test` yields ["path-to-cargo-pretty-test", rest]
         &passin[1..]
     };
-    if forward.iter().any(|arg| arg == "--version" || arg == "-V") {
-        const VERSION: &str = env!("CARGO_PKG_VERSION");
-        println!("cargo-pretty-test version: {VERSION}");
+    let mut args = Args::parse(passin.iter().cloned()).unwrap_or_else(|err| invalid_arg(err));
+    if args.version || args.help {
+        set_theme(icons(&args));
+        if args.version {
+            const VERSION: &str = env!("CARGO_PKG_VERSION");
+            println!("cargo-pretty-test version: {VERSION}");
+        }
         return Emit {
-            output: None,
-            no_parse: true,
+            cmds: Vec::new(),
+            rerun_failed: false,
+            help: args.help,
+            junit: None,
+            step_summary: None,
+            format: Format::Pretty,
+            libtest_json: false,
+            stream: false,
+            from_file: None,
+            tree: Options::default(),
+            slowest: None,
+            retry: None,
+            compare: None,
+            watch: None,
+            targets: Vec::new(),
+            forward: args.forward(),
         };
     }
-    set_color(forward);
-    let no_parse = forward.iter().any(|arg| arg == "--help" || arg == "-h");
-    let args = forward.iter().filter(|&arg| arg != "--nocapture");
+    apply_config(&mut args);
+    set_theme(icons(&args));
+    let mut forward = args.forward();
+    let from_file = args.value("from-file").map(String::from);
+    let mut libtest_json = args.flag("libtest-json");
+    let tree = tree_options(&args);
+    set_color(&forward);
+    if libtest_json && from_file.is_some() {
+        // the log is supposed to contain JSON events
+    } else if libtest_json {
+        if toolchain_supports_json() {
+            add_libtest_json_args(&mut forward);
+        } else {
+            eprintln!(
+                "{}",
+                "--libtest-json requires a nightly toolchain or RUSTC_BOOTSTRAP; \
+                 falling back to parsing texts"
+                    .yellow()
+            );
+            libtest_json = false;
+        }
+    }
+    let nocapture = args.test_bins.iter().any(|arg| arg == "--nocapture");
+    if nocapture
+        && !args
+            .test_bins
+            .iter()
+            .any(|a| a.starts_with("--test-threads"))
+    {
+        forward.push("--test-threads=1".into());
+    }
+    let retries = number(&args, "retries").unwrap_or(0);
+    let retry = (retries != 0 && live_run(&args, "retries")).then(|| Retry {
+        times: retries,
+        fail_on_flaky: args.flag("fail-on-flaky"),
+    });
+    let watch = live_run(&args, "watch").then(|| watch_workspace(&forward));
+    // the commands of `--rerun-failed` are built before each run
+    let rerun_failed = live_run(&args, "rerun-failed");
+    let mut cmd = Command::new("cargo");
+    cmd.arg("test").args(&forward);
     Emit {
-        output: Some(
-            Command::new("cargo")
-                .arg("test")
-                .args(args)
-                .output()
-                .expect("`cargo test` failed"),
-        ),
-        no_parse,
+        cmds: vec![cmd],
+        rerun_failed,
+        help: false,
+        junit: args.value("junit").map(PathBuf::from),
+        step_summary: std::env::var_os("GITHUB_STEP_SUMMARY")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from),
+        format: format(&args),
+        libtest_json,
+        // the output printed by tests breaks the lines being streamed
+        stream: live_run(&args, "stream") && (libtest_json || !nocapture),
+        from_file,
+        tree,
+        slowest: number(&args, "slowest"),
+        retry,
+        compare: args.value("compare").map(String::from),
+        watch,
+        targets: targets(&args),
+        forward,
     }
 }
 
+/// Whether the option that needs a running `cargo test` is given. Streaming reads the
+/// output of the child process while retrying, rerunning and watching spawn new ones,
+/// but a saved log of `--from-file` has no process behind it, so they're ignored.
+fn live_run(args: &Args, name: &str) -> bool {
+    args.flag(name) && !args.given("from-file")
+}
+
+/// Watch the source directories of all the packages in the workspace.
+fn watch_workspace(forward: &[String]) -> Watcher {
+    let meta = Metadata::load(forward).unwrap_or_else(|err| {
+        invalid_arg(format!(
+            "can't watch the workspace, `cargo metadata` failed: {err}"
+        ))
+    });
+    let roots = meta
+        .packages
+        .iter()
+        .filter_map(|pkg| Path::new(&pkg.manifest_path).parent())
+        .flat_map(|dir| WATCHED_DIRS.map(|sub| dir.join(sub)))
+        .collect();
+    Watcher::new(roots)
+}
+
+/// Make a `cargo test` command for each target that has failed tests in the last run.
+fn rerun_failed_commands(forward: &[String]) -> Result<Vec<Command>, String> {
+    let meta =
+        Metadata::load(forward).map_err(|err| format!("Failed to run `cargo metadata`: {err}"))?;
+    let failed = load_failed(&meta.target_directory)
+        .map_err(|err| format!("Failed to read the last failed tests: {err}"))?;
+    if failed.is_empty() {
+        println!("{}", "No failed tests in the last run".green().bold());
+    }
+    Ok(rerun_args(&meta, &failed, forward)
+        .into_iter()
+        .map(|args| {
+            let mut cmd = Command::new("cargo");
+            cmd.arg("test").args(args);
+            cmd
+        })
+        .collect())
+}
+
+/// Read a saved log from the file or stdin (for `-`), and split it into `Output`
+/// as if it's produced by `cargo test`.
+fn read_log(path: &str) -> std::io::Result<Output> {
+    let text = if path == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(path)?
+    };
+    let (stderr, stdout) = split_combined_output(&text);
+    Ok(Output {
+        status: ExitStatus::default(),
+        stdout: stdout.into_bytes(),
+        stderr: stderr.into_bytes(),
+    })
+}
+
+/// Apply the configured options to the arguments, and exit if the configuration is invalid.
+fn apply_config(args: &mut Args) {
+    if let Err(err) = config::apply(args) {
+        invalid_arg(format!("invalid configuration: {err}"));
+    }
+}
+
+/// Get `--format` or `--tui`, and exit if the format is unknown.
+fn format(args: &Args) -> Format {
+    match args.value("format").map(str::parse) {
+        Some(Ok(format)) => format,
+        Some(Err(err)) => invalid_arg(err),
+        None if args.flag("tui") => Format::Tui,
+        None => Format::Pretty,
+    }
+}
+
+/// Get the target triples from `--target` or `CARGO_BUILD_TARGET`, where the triple of
+/// a custom target spec like `path/to/my-target.json` is its file stem.
+fn targets(args: &Args) -> Vec<String> {
+    let env = std::env::var("CARGO_BUILD_TARGET").ok();
+    let given = args.cargo_values("target");
+    let targets = if given.is_empty() {
+        env.as_deref().into_iter().collect()
+    } else {
+        given
+    };
+    targets
+        .into_iter()
+        .map(|target| match target.strip_suffix(".json") {
+            Some(_) => Path::new(target)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or(target),
+            None => target,
+        })
+        .map(String::from)
+        .collect()
+}
+
+/// Get the options to render the test tree.
+fn tree_options(args: &Args) -> Options {
+    Options {
+        inline_failures: args.flag("inline-failures"),
+        failure_lines: number(args, "failure-lines").unwrap_or(Options::default().failure_lines),
+        statuses: statuses(args),
+        collapse_passing: args.flag("collapse-passing"),
+        module_counts: args.flag("module-counts"),
+        sort: sort(args),
+    }
+}
+
+/// Get `--sort` as the order of the tree, and exit if it's unknown.
+fn sort(args: &Args) -> Sort {
+    args.value("sort").map_or(Sort::Name, |s| {
+        s.parse().unwrap_or_else(|err: String| invalid_arg(err))
+    })
+}
+
+/// Get `--only` and `--hide` as the statuses of tests to show, and exit if any is unknown.
+fn statuses(args: &Args) -> StatusFilter {
+    let parse = |name| {
+        args.value(name).map(|list| {
+            StatusFilter::parse(list)
+                .unwrap_or_else(|err| invalid_arg(format!("invalid --{name}: {err}")))
+        })
+    };
+    let only = parse("only").unwrap_or(StatusFilter::ALL);
+    let hide = parse("hide").unwrap_or(StatusFilter::NONE);
+    [Status::Ok, Status::Ignored, Status::Failed, Status::Flaky]
+        .into_iter()
+        .filter(|&status| hide.contains(status))
+        .fold(only, StatusFilter::without)
+}
+
+/// Get `--icons` as the theme, and exit if it's invalid.
+fn icons(args: &Args) -> Theme {
+    args.value("icons").map_or_else(Theme::default, |spec| {
+        Theme::parse(spec).unwrap_or_else(|err| invalid_arg(format!("invalid --icons: {err}")))
+    })
+}
+
+/// Get the value of the option as a number, and exit if it isn't one.
+fn number(args: &Args, name: &str) -> Option<usize> {
+    args.value(name).map(|n| {
+        n.parse()
+            .unwrap_or_else(|err| invalid_arg(format!("invalid --{name} `{n}`: {err}")))
+    })
+}
+
+/// Report the invalid arguments or configuration, and exit like cargo does for them.
+fn invalid_arg(msg: impl std::fmt::Display) -> ! {
+    eprintln!("{}: {msg}", "Error from cargo-pretty-test".red().bold());
+    std::process::exit(2);
+}
+
+/// List the `n` slowest tests with their package and source file.
+pub fn slowest_report(runners: &TestRunners, n: usize) -> String {
+    let slowest = runners.slowest(n);
+    if slowest.is_empty() {
+        return format!(
+            "{} none of the tests reports its duration; use --libtest-json \
+             or `-- -Z unstable-options --report-time`",
+            "Slowest tests:".bold()
+        );
+    }
+    let mut report = format!("{}", format!("Slowest {} tests:", slowest.len()).bold());
+    for (pkg, runner, test) in slowest {
+        let duration = test.duration.map(format_duration).unwrap_or_default();
+        _ = write!(
+            report,
+            "\n  {duration} {} {} {}",
+            pkg.unwrap_or("tests").blue(),
+            runner.src.src_path,
+            test.name
+        );
+    }
+    report
+}
+
+/// Append the text to the file, which is created if missing.
+fn append(path: &Path, text: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{text}")
+}
+
+/// Serialize the whole parsed result into pretty-printed JSON.
+#[cfg(feature = "serde")]
+pub fn json_report(runners: &TestRunners) -> Result<String> {
+    serde_json::to_string_pretty(runners).map_err(|err| crate::Error::JsonOutput(err.to_string()))
+}
+
+/// Browse the test tree in the terminal UI until quitting.
+#[cfg(feature = "tui")]
+fn browse(runners: &TestRunners, forward: &[String], libtest_json: bool) -> std::io::Result<()> {
+    crate::tui::browse(runners, forward.to_vec(), libtest_json)
+}
+
+/// Browse the test tree in the terminal UI until quitting.
+#[cfg(not(feature = "tui"))]
+fn browse(_: &TestRunners, _: &[String], _: bool) -> std::io::Result<()> {
+    Err(std::io::Error::other(
+        "cargo-pretty-test is not built with the `tui` feature",
+    ))
+}
+
 /// reintepret `--color`
 fn set_color(forward: &[String]) {
     fn detect_env() {
@@ -130,22 +862,45 @@ pub fn parse_cargo_test_output<'s>(
     stderr: &'s str,
     stdout: &'s str,
 ) -> Result<(TestTree<'s>, Stats)> {
+    Ok(make_test_tree(
+        &parse_cargo_test(stderr, stdout)?,
+        &Options::default(),
+    ))
+}
+
+/// Build the whole test tree and sum up the statistics from parsed test runners.
+///
+/// Note: error details from `cargo test` are printed to stderr when building
+/// unless failures are shown inline.
+pub fn make_test_tree<'s>(runners: &TestRunners<'s>, opts: &Options) -> (TestTree<'s>, Stats) {
     let mut tree = Tree::new("Generated by cargo-pretty-test".bold().to_string().into());
     let mut stats = Stats::default();
-    for (pkg, data) in parse_cargo_test(stderr, stdout)?.pkgs {
+    for (pkg, data) in &runners.pkgs {
+        // the summary counts all the tests even if some are hidden
         stats += &data.stats;
         let root = data.stats.root_string(pkg.unwrap_or("tests")).into();
-        tree.push(
-            Tree::new(root).with_leaves(data.inner.into_iter().filter_map(|data| {
-                let parsed = data.info.parsed;
-                let detail_without_stats = parsed.detail;
-                if !detail_without_stats.is_empty() {
-                    eprintln!("{detail_without_stats}\n\n{}\n", re().separator);
-                }
-                let root = data.info.stats.subroot_string(data.runner.src.src_path);
-                make_pretty(root, parsed.tree.into_iter())
-            })),
-        );
+        let leaves: Vec<_> = data
+            .inner
+            .iter()
+            .filter_map(|data| make_runner_tree(data, opts))
+            .collect();
+        if leaves.is_empty() && !opts.statuses.is_all() {
+            continue;
+        }
+        tree.push(Tree::new(root).with_leaves(leaves));
+    }
+    (tree, stats)
+}
+
+/// Build the test tree for a test runner.
+///
+/// Note: error details from `cargo test` are printed to stderr when building
+/// unless failures are shown inline.
+pub fn make_runner_tree<'s>(data: &Data<'s>, opts: &Options) -> Option<TestTree<'s>> {
+    let detail_without_stats = data.info.parsed.detail;
+    if !detail_without_stats.is_empty() && !opts.inline_failures {
+        eprintln!("{detail_without_stats}\n\n{}\n", re().separator);
     }
-    Ok((tree, stats))
+    let root = data.info.stats.subroot_string(data.runner.src.src_path);
+    runner_tree(root, data, opts)
 }
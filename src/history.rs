@@ -0,0 +1,302 @@
+//! Per-test results of past runs, and comparing a run against one of them.
+//!
+//! Each run is saved as `<target-dir>/pretty-test/history/<run-id>`, where the run id is
+//! the start time in milliseconds since the Unix epoch. Each line of it is a test with
+//! tab-separated package, source path of the test runner, test name, status and duration
+//! in seconds, the last of which is empty if not reported.
+
+use crate::{
+    parsing::{TestCase, TestRunners},
+    prettify::Status,
+    rerun::DATA_DIR,
+};
+use colored::Colorize;
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Error},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const HISTORY: &str = "history";
+
+/// Older runs are removed when saving a run beyond the limit.
+const MAX_RUNS: usize = 50;
+
+/// A test is significantly slower if it takes more than this times as long as before...
+const SLOWER_RATIO: f64 = 2.0;
+/// ... and the difference is at least this long, which ignores the noise of fast tests.
+const SLOWER_MIN: Duration = Duration::from_millis(100);
+
+/// The result of a test in a past run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub pkg: String,
+    pub src_path: String,
+    pub name: String,
+    pub status: Status,
+    pub duration: Option<Duration>,
+}
+
+/// A past run loaded from the history.
+#[derive(Debug)]
+pub struct Run {
+    pub id: String,
+    pub records: Vec<Record>,
+}
+
+/// How a test changed since the compared run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Change {
+    NewlyFailing,
+    NewlyPassing,
+    /// Not in the compared run.
+    New,
+    /// Significantly slower than the duration in the compared run.
+    Slower {
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::parsing::serialize_secs")
+        )]
+        before: Duration,
+    },
+}
+
+impl Change {
+    /// The mark shown beside the test in the tree.
+    pub fn mark(self) -> String {
+        match self {
+            Change::NewlyFailing => "[newly failing]".red().bold().to_string(),
+            Change::NewlyPassing => "[newly passing]".green().bold().to_string(),
+            Change::New => "[new]".cyan().to_string(),
+            Change::Slower { before } => format!("[slower than {:.3}s]", before.as_secs_f64())
+                .yellow()
+                .to_string(),
+        }
+    }
+}
+
+/// Differences between the current run and a past run.
+#[derive(Debug, Default)]
+pub struct Diff {
+    /// The id of the compared run.
+    pub base: String,
+    pub newly_failing: u32,
+    pub newly_passing: u32,
+    pub new: u32,
+    pub slower: u32,
+    /// Tests in the compared run but not in the current one.
+    pub removed: Vec<Record>,
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Diff {
+            base,
+            newly_failing,
+            newly_passing,
+            new,
+            slower,
+            removed,
+        } = self;
+        let removed_len = removed.len();
+        write!(
+            f,
+            "Compared with run {base}: {newly_failing} newly failing; {newly_passing} newly passing; \
+             {new} new; {removed_len} removed; {slower} slower"
+        )?;
+        for record in removed {
+            let pkg = if record.pkg.is_empty() {
+                "tests"
+            } else {
+                &record.pkg
+            };
+            write!(
+                f,
+                "\n  {} {} {} {}",
+                "removed".bright_black(),
+                pkg.blue(),
+                record.src_path,
+                record.name
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the status counts as passing when comparing runs.
+fn passing(status: Status) -> bool {
+    matches!(status, Status::Ok | Status::Flaky)
+}
+
+/// The change of a test compared with its record in the past run.
+fn change_of(test: &TestCase, record: Option<&Record>) -> Option<Change> {
+    let Some(record) = record else {
+        return Some(Change::New);
+    };
+    match (record.status, test.status) {
+        (Status::Failed, now) if passing(now) => return Some(Change::NewlyPassing),
+        (before, Status::Failed) if passing(before) => return Some(Change::NewlyFailing),
+        _ => (),
+    }
+    let (before, now) = (record.duration?, test.duration?);
+    let slower = now.as_secs_f64() > before.as_secs_f64() * SLOWER_RATIO
+        && now.saturating_sub(before) >= SLOWER_MIN;
+    slower.then_some(Change::Slower { before })
+}
+
+/// Mark the change of each test since the past run, and sum up the differences.
+pub fn compare(runners: &mut TestRunners, base: &Run) -> Diff {
+    let mut records: HashMap<_, _> = base
+        .records
+        .iter()
+        .map(|r| ((r.pkg.as_str(), r.src_path.as_str(), r.name.as_str()), r))
+        .collect();
+    let mut diff = Diff {
+        base: base.id.clone(),
+        ..Diff::default()
+    };
+    for (pkg, pkg_test) in &mut runners.pkgs {
+        let pkg = pkg.unwrap_or_default();
+        for data in &mut pkg_test.inner {
+            let src_path = data.runner.src.src_path;
+            for test in &mut data.info.parsed.tests {
+                let record = records.remove(&(pkg, src_path, test.name));
+                test.change = change_of(test, record);
+                match test.change {
+                    Some(Change::NewlyFailing) => diff.newly_failing += 1,
+                    Some(Change::NewlyPassing) => diff.newly_passing += 1,
+                    Some(Change::New) => diff.new += 1,
+                    Some(Change::Slower { .. }) => diff.slower += 1,
+                    None => (),
+                }
+            }
+        }
+    }
+    // keep the order in the compared run
+    diff.removed = base
+        .records
+        .iter()
+        .filter(|r| records.contains_key(&(r.pkg.as_str(), r.src_path.as_str(), r.name.as_str())))
+        .cloned()
+        .collect();
+    diff
+}
+
+fn history_dir(target_dir: &Path) -> PathBuf {
+    target_dir.join(DATA_DIR).join(HISTORY)
+}
+
+/// Ids of the saved runs, oldest first.
+pub fn run_ids(target_dir: &Path) -> io::Result<Vec<String>> {
+    let entries = match std::fs::read_dir(history_dir(target_dir)) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut ids = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name();
+        if let Some(id) = name.to_str().filter(|id| id.parse::<u128>().is_ok()) {
+            ids.push(id.to_owned());
+        }
+    }
+    ids.sort_by_key(|id| id.parse::<u128>().unwrap_or_default());
+    Ok(ids)
+}
+
+/// Save the results of the run in the history, and return the run id.
+pub fn save_run(target_dir: &Path, runners: &TestRunners) -> io::Result<String> {
+    let dir = history_dir(target_dir);
+    std::fs::create_dir_all(&dir)?;
+    let id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+        .to_string();
+    let mut text = String::new();
+    for (pkg, pkg_test) in &runners.pkgs {
+        for data in &pkg_test.inner {
+            for test in &data.info.parsed.tests {
+                let status = test.status.name();
+                let duration = test
+                    .duration
+                    .map(|d| d.as_secs_f64().to_string())
+                    .unwrap_or_default();
+                text.extend([
+                    pkg.unwrap_or_default(),
+                    "\t",
+                    data.runner.src.src_path,
+                    "\t",
+                    test.name,
+                    "\t",
+                    status,
+                    "\t",
+                    &duration,
+                    "\n",
+                ]);
+            }
+        }
+    }
+    std::fs::write(dir.join(&id), text)?;
+    let ids = run_ids(target_dir)?;
+    for old in &ids[..ids.len().saturating_sub(MAX_RUNS)] {
+        std::fs::remove_file(dir.join(old))?;
+    }
+    Ok(id)
+}
+
+/// Load a saved run by its id, or the latest one for `last`.
+pub fn load_run(target_dir: &Path, id: &str) -> io::Result<Run> {
+    let id = if id == "last" {
+        run_ids(target_dir)?
+            .pop()
+            .ok_or_else(|| Error::other("no run in the history yet"))?
+    } else {
+        id.to_owned()
+    };
+    let text = std::fs::read_to_string(history_dir(target_dir).join(&id)).map_err(|err| {
+        if err.kind() == io::ErrorKind::NotFound {
+            Error::other(format!("no run `{id}` in the history"))
+        } else {
+            err
+        }
+    })?;
+    let records = text
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let invalid = || Error::other(format!("invalid record of run {id}: {line:?}"));
+            let mut fields = line.split('\t');
+            let mut next = || fields.next().ok_or_else(invalid);
+            let (pkg, src_path, name) = (next()?, next()?, next()?);
+            let status = match next()? {
+                "ok" => Status::Ok,
+                "ignored" => Status::Ignored,
+                "failed" => Status::Failed,
+                "flaky" => Status::Flaky,
+                _ => return Err(invalid()),
+            };
+            let duration = match next()? {
+                "" => None,
+                secs => Some(
+                    secs.parse()
+                        .ok()
+                        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                        .ok_or_else(invalid)?,
+                ),
+            };
+            Ok(Record {
+                pkg: pkg.to_owned(),
+                src_path: src_path.to_owned(),
+                name: name.to_owned(),
+                status,
+                duration,
+            })
+        })
+        .collect::<io::Result<_>>()?;
+    Ok(Run { id, records })
+}
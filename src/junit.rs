@@ -0,0 +1,113 @@
+//! Generate a JUnit XML report which is understood by most CI services.
+//!
+//! Each test runner becomes a `<testsuite>`, and each line in its test tree
+//! becomes a `<testcase>`.
+
+use crate::{
+    parsing::{Data, TestCase, TestRunners, Text},
+    prettify::Status,
+};
+use std::fmt::Write;
+
+/// Render the JUnit XML report for all the test runners.
+pub fn junit_report(runners: &TestRunners) -> String {
+    let stats = runners.stats();
+    let mut suites = String::new();
+    for (pkg, data) in &runners.pkgs {
+        for data in &data.inner {
+            write_testsuite(&mut suites, pkg.unwrap_or("tests"), data);
+        }
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites name=\"cargo-pretty-test\" tests=\"{}\" failures=\"{}\" \
+         skipped=\"{}\" time=\"{:.3}\">\n{suites}</testsuites>\n",
+        stats.total,
+        stats.failed,
+        stats.ignored,
+        stats.finished_in.as_secs_f64()
+    )
+}
+
+fn write_testsuite(xml: &mut String, pkg: Text, data: &Data) {
+    let src = data.runner.src;
+    let name = if src.bin_name == src.src_path {
+        escape(src.bin_name)
+    } else {
+        escape(&format!("{} ({})", src.bin_name, src.src_path))
+    };
+    let stats = &data.info.stats;
+    _ = writeln!(
+        xml,
+        "  <testsuite name=\"{name}\" package=\"{}\" tests=\"{}\" failures=\"{}\" \
+         skipped=\"{}\" time=\"{:.3}\">",
+        escape(pkg),
+        stats.total,
+        stats.failed,
+        stats.ignored,
+        stats.finished_in.as_secs_f64()
+    );
+    let detail = data.info.parsed.detail;
+    for test in &data.info.parsed.tests {
+        write_testcase(xml, &name, test, detail);
+    }
+    xml.push_str("  </testsuite>\n");
+}
+
+fn write_testcase(xml: &mut String, classname: &str, test: &TestCase, detail: Text) {
+    let name = escape(test.name);
+    _ = write!(
+        xml,
+        "    <testcase name=\"{name}\" classname=\"{classname}\""
+    );
+    if let Some(duration) = test.duration {
+        _ = write!(xml, " time=\"{:.3}\"", duration.as_secs_f64());
+    }
+    match test.status {
+        Status::Ok => xml.push_str("/>\n"),
+        Status::Ignored => {
+            xml.push_str(">\n      <skipped");
+            if let Some(reason) = test.ignore_reason {
+                _ = write!(xml, " message=\"{}\"", escape(reason));
+            }
+            xml.push_str("/>\n    </testcase>\n");
+        }
+        Status::Failed | Status::Flaky => {
+            // fall back to the whole detail if the failure block can't be found
+            let (message, body) = test.failure.as_ref().map_or_else(
+                || (format!("{name} failed"), detail),
+                |f| (escape(f.message), f.stdout),
+            );
+            // a flaky test passes in the end, which is reported like Maven Surefire's reruns
+            let tag = if test.status == Status::Flaky {
+                "flakyFailure"
+            } else {
+                "failure"
+            };
+            _ = write!(
+                xml,
+                ">\n      <{tag} message=\"{message}\">{}</{tag}>\n    </testcase>\n",
+                escape(body)
+            );
+        }
+    }
+}
+
+/// Escape the text for XML attributes and content, dropping characters
+/// that are not allowed in XML 1.0.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -56,10 +56,26 @@
 #[doc(hidden)]
 pub mod doc;
 
+pub mod args;
+pub mod config;
+pub mod diagnostic;
+pub mod error;
+pub mod events;
+pub mod failure;
 pub mod fetch;
+pub mod history;
+pub mod junit;
+pub mod markdown;
+pub mod output;
 pub mod parsing;
 pub mod prettify;
 pub mod regex;
+pub mod rerun;
+pub mod stream;
+pub mod theme;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod watch;
 
-pub type Error = String;
+pub use error::Error;
 pub type Result<T, E = Error> = ::std::result::Result<T, E>;
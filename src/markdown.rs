@@ -0,0 +1,184 @@
+//! Render a Markdown report for GitHub step summaries and PR comments.
+//!
+//! The report has a headline status, a table of the packages, a collapsible test tree
+//! for each test runner, and the output of failed tests in code blocks.
+
+use crate::{
+    diagnostic::BuildFailure,
+    parsing::{Data, Stats, TestRunners, Text},
+    prettify::{runner_tree, Options, Status},
+};
+use std::fmt::Write;
+
+/// Render the Markdown report for all the test runners. Colors are stripped, and the trees
+/// are rendered with the options like the pretty format.
+pub fn markdown_report(runners: &TestRunners, opts: &Options) -> String {
+    let stats = runners.stats();
+    let mut md = String::new();
+    _ = writeln!(
+        md,
+        "## {} cargo-pretty-test: {}\n\n{}\n",
+        status_icon(stats.ok),
+        if stats.ok { "OK" } else { "FAIL" },
+        plain(&stats.to_string())
+    );
+
+    md.push_str(
+        "| Package | Status | Tests | Passed | Failed | Flaky | Ignored | Filtered out | Time |\n\
+         | --- | :---: | ---: | ---: | ---: | ---: | ---: | ---: | ---: |\n",
+    );
+    for (pkg, data) in &runners.pkgs {
+        let Stats {
+            ok,
+            total,
+            passed,
+            failed,
+            flaky,
+            ignored,
+            filtered_out,
+            finished_in,
+            ..
+        } = data.stats;
+        _ = writeln!(
+            md,
+            "| {} | {} | {total} | {passed} | {failed} | {flaky} | {ignored} | {filtered_out} \
+             | {:.2}s |",
+            escape_cell(pkg.unwrap_or("tests")),
+            status_icon(ok),
+            finished_in.as_secs_f64()
+        );
+    }
+
+    for (pkg, data) in &runners.pkgs {
+        for data in &data.inner {
+            write_runner(&mut md, pkg.unwrap_or("tests"), data, opts);
+        }
+    }
+
+    let mut failures = String::new();
+    for (pkg, data) in &runners.pkgs {
+        for data in &data.inner {
+            write_failures(&mut failures, pkg.unwrap_or("tests"), data);
+        }
+    }
+    if !failures.is_empty() {
+        _ = write!(md, "\n### Failures\n{failures}");
+    }
+    md
+}
+
+/// Render the Markdown report for compiler errors that stopped `cargo test` before running
+/// any test.
+pub fn markdown_build_failure(failure: &BuildFailure) -> String {
+    format!(
+        "## {} cargo-pretty-test: Build failed\n\n{}",
+        status_icon(false),
+        code_block(&plain(&failure.make_tree().to_string()))
+    )
+}
+
+/// A `<details>` block with the test tree of the runner, which is open if any test failed.
+fn write_runner(md: &mut String, pkg: Text, data: &Data, opts: &Options) {
+    let src_path = data.runner.src.src_path;
+    let Some(tree) = runner_tree(src_path, data, opts) else {
+        return;
+    };
+    let stats = &data.info.stats;
+    _ = write!(
+        md,
+        "\n<details{}>\n<summary>{} {}: {} ({})</summary>\n\n{}\n</details>\n",
+        if stats.ok { "" } else { " open" },
+        status_icon(stats.ok),
+        escape_html(pkg),
+        escape_html(src_path),
+        plain(&stats.inlay_summary_string()),
+        code_block(&plain(&tree.to_string()))
+    );
+}
+
+/// A section of each failed or flaky test with its output. Tests whose failure block
+/// can't be found share a section with the whole detail of the runner.
+fn write_failures(md: &mut String, pkg: Text, data: &Data) {
+    let parsed = &data.info.parsed;
+    let runner = code_span(&format!("{pkg} {}", data.runner.src.src_path));
+    let failed = parsed
+        .tests
+        .iter()
+        .filter(|test| matches!(test.status, Status::Failed | Status::Flaky));
+    let mut without_block = Vec::new();
+    for test in failed {
+        let Some(failure) = &test.failure else {
+            without_block.push(test);
+            continue;
+        };
+        let flaky = if test.status == Status::Flaky {
+            " (flaky)"
+        } else {
+            ""
+        };
+        _ = write!(
+            md,
+            "\n#### {} {runner}: {}{flaky}\n\n{}",
+            test.status.symbol(),
+            code_span(test.name),
+            code_block(failure.stdout)
+        );
+    }
+    let Some(status) = without_block.iter().map(|test| test.status).max() else {
+        return;
+    };
+    let names: Vec<_> = without_block
+        .iter()
+        .map(|test| code_span(test.name))
+        .collect();
+    _ = write!(
+        md,
+        "\n#### {} {runner}: {}\n\n{}",
+        status.symbol(),
+        names.join(", "),
+        code_block(parsed.detail)
+    );
+}
+
+fn status_icon(ok: bool) -> &'static str {
+    if ok {
+        Status::Ok.symbol()
+    } else {
+        Status::Failed.symbol()
+    }
+}
+
+/// Strip ANSI escapes of colors.
+fn plain(text: &str) -> String {
+    String::from_utf8_lossy(&strip_ansi_escapes::strip(text)).into_owned()
+}
+
+/// A fenced code block, whose fence is longer than any run of backticks in the text.
+fn code_block(text: &str) -> String {
+    let fence = "`".repeat(longest_backticks(text).max(2) + 1);
+    format!("{fence}text\n{}\n{fence}\n", text.trim_end())
+}
+
+/// An inline code span, like `` `name` ``.
+fn code_span(text: &str) -> String {
+    let ticks = "`".repeat(longest_backticks(text) + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{ticks} {text} {ticks}")
+    } else {
+        format!("{ticks}{text}{ticks}")
+    }
+}
+
+fn longest_backticks(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+fn escape_cell(text: &str) -> String {
+    escape_html(text).replace('|', "\\|")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,212 @@
+//! Output printed by tests with `--show-output` or `--nocapture`.
+//!
+//! `--show-output` puts the output of passed tests in a `successes:` section before the
+//! failures section, in the same form as the latter:
+//!
+//! ```text
+//! successes:
+//!
+//! ---- tests::prints stdout ----
+//! hello from prints
+//!
+//!
+//! successes:
+//!     tests::prints
+//!     tests::quiet
+//! ```
+//!
+//! With `--nocapture`, the output goes to stdout as soon as it's printed, which splits the line
+//! of the running test if tests run one at a time:
+//!
+//! ```text
+//! test tests::prints ... hello from prints
+//! ok
+//! ```
+//!
+//! [`attribute_nocapture`] moves such output into the `successes:` and `failures:` sections,
+//! so both flags end up with the same text.
+
+use crate::{failure::stdout_blocks, parsing::Text};
+use std::{borrow::Cow, fmt::Write};
+
+const SUCCESSES: &str = "successes:";
+
+/// Split the detail of a test runner into the leading `successes:` section, which is empty
+/// without `--show-output`, and the rest.
+pub fn split_successes(detail: Text<'_>) -> (Text<'_>, Text<'_>) {
+    if !detail.starts_with(SUCCESSES) {
+        return ("", detail);
+    }
+    let mut pos = 0;
+    let mut headers = 0;
+    for line in detail.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == SUCCESSES {
+            headers += 1;
+        } else if headers == 2 && !line.starts_with("    ") {
+            // the end of the list of test names after the blocks
+            break;
+        }
+        pos += line.len();
+    }
+    (detail[..pos].trim(), detail[pos..].trim())
+}
+
+/// Output of each passed test in the `successes:` section.
+pub fn parse_successes(section: Text<'_>) -> Vec<(Text<'_>, Text<'_>)> {
+    stdout_blocks(section, SUCCESSES)
+}
+
+/// Whether the rest of a test line is the status, like `ok`, `FAILED`, `ignored, reason`
+/// or `ok <0.001s>`.
+fn is_status(s: &str) -> bool {
+    let s = s.split_once(" <").map_or(s, |(status, _)| status);
+    matches!(s, "ok" | "FAILED" | "ignored") || s.starts_with("ignored, ")
+}
+
+/// Split the last line of output before the status printed right after it, like
+/// `partialok` for output without a trailing line break, into `("partial", "ok")`.
+fn split_status_suffix(line: &str) -> Option<(&str, &str)> {
+    let pos = line
+        .char_indices()
+        .map(|(i, _)| i)
+        .find(|&i| is_status(&line[i..]))?;
+    Some(line.split_at(pos))
+}
+
+/// Split a test line interrupted by output into the head like `test tests::prints ...`,
+/// the test name and the start of the output.
+fn split_test_line(line: &str) -> Option<(&str, &str, &str)> {
+    let rest = line.strip_prefix("test ")?;
+    let (name, output) = rest.split_once(" ... ")?;
+    let head = &line[..5 + name.len() + 4];
+    (!is_status(output)).then_some((head, name, output))
+}
+
+/// Whether the line can't be the output of the running test, like the next test line.
+fn ends_running(line: &str) -> bool {
+    line.strip_prefix("test ")
+        .is_some_and(|rest| rest.contains(" ... ") || rest.starts_with("result: "))
+        || matches!(line, "failures:" | "successes:")
+}
+
+/// A test line interrupted by output.
+struct Running<'s> {
+    /// Like `test tests::prints ...`.
+    head: &'s str,
+    name: &'s str,
+    /// The output so far, where each line ends with a line break.
+    output: String,
+}
+
+/// Sections of test output being collected, and the text with test lines restored.
+#[derive(Default)]
+struct Attributed {
+    text: String,
+    successes: String,
+    failures: String,
+}
+
+impl Attributed {
+    /// Restore the test line with the status, and add the output to the section.
+    fn finish(&mut self, running: &Running, status: &str, output: &str) {
+        _ = writeln!(self.text, "{} {status}", running.head);
+        let section = if status.starts_with("FAILED") {
+            &mut self.failures
+        } else {
+            &mut self.successes
+        };
+        block(section, running.name, output);
+    }
+
+    /// Finish the test whose status isn't on a line of its own, but right after the last
+    /// line of output without a line break. The test line is left as is if there's no status.
+    fn finish_interrupted(&mut self, running: &Running) {
+        let output = running.output.trim_end_matches('\n');
+        let (before, last) = output.rsplit_once('\n').unwrap_or(("", output));
+        match split_status_suffix(last) {
+            Some((rest, status)) => {
+                let output = if before.is_empty() {
+                    rest.to_owned()
+                } else {
+                    format!("{before}\n{rest}")
+                };
+                self.finish(running, status, &output);
+            }
+            None => _ = writeln!(self.text, "{} {output}", running.head),
+        }
+    }
+}
+
+/// Move the output printed by tests with `--nocapture` from the test lines into the
+/// `successes:` or `failures:` section of each test runner.
+///
+/// The status of a test follows its output, either on a line of its own or right after
+/// the last line of output without a line break, like `test tests::prints ... partialok`.
+/// Output that isn't in a test line is left as is, like the one printed while multiple
+/// tests run at the same time.
+pub fn attribute_nocapture(stdout: &str) -> Cow<'_, str> {
+    if !stdout.lines().any(|line| split_test_line(line).is_some()) {
+        return Cow::Borrowed(stdout);
+    }
+    let mut out = Attributed {
+        text: String::with_capacity(stdout.len()),
+        ..Attributed::default()
+    };
+    let mut running: Option<Running> = None;
+    for line in stdout.lines() {
+        if let Some(test) = &mut running {
+            if is_status(line) {
+                let output = std::mem::take(&mut test.output);
+                out.finish(test, line, &output);
+                running = None;
+                continue;
+            }
+            if !ends_running(line) {
+                test.output.push_str(line);
+                test.output.push('\n');
+                continue;
+            }
+            out.finish_interrupted(test);
+            running = None;
+        }
+        if let Some((head, name, output)) = split_test_line(line) {
+            running = Some(Running {
+                head,
+                name,
+                output: format!("{output}\n"),
+            });
+            continue;
+        }
+        let Attributed {
+            text,
+            successes,
+            failures,
+        } = &mut out;
+        let is_result = line.starts_with("test result: ");
+        if (line == "failures:" || is_result) && !successes.is_empty() {
+            _ = write!(text, "\n{SUCCESSES}\n{successes}\n{SUCCESSES}\n\n");
+            successes.clear();
+        }
+        _ = writeln!(text, "{line}");
+        if line == "failures:" && !failures.is_empty() {
+            text.push_str(failures);
+            failures.clear();
+        }
+    }
+    if let Some(test) = &running {
+        out.finish_interrupted(test);
+    }
+    Cow::Owned(out.text)
+}
+
+/// Add the block of a test with its output to the section unless it printed nothing.
+fn block(section: &mut String, name: &str, output: &str) {
+    if !output.trim().is_empty() {
+        _ = write!(
+            section,
+            "\n---- {name} stdout ----\n{}\n\n",
+            output.trim_end()
+        );
+    }
+}
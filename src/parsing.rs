@@ -1,7 +1,16 @@
-use crate::{regex::re, Result};
+use crate::{
+    failure::{parse_failures, Failure},
+    history::Change,
+    output::{parse_successes, split_successes},
+    prettify::Status,
+    regex::re,
+    theme::theme,
+    Error, Result,
+};
 use colored::{ColoredString, Colorize};
 use indexmap::IndexMap;
 use std::{
+    cmp::Reverse,
     path::{Component, Path},
     time::Duration,
 };
@@ -9,16 +18,12 @@ use std::{
 /// The core parsing function that extracts all the information from `cargo test`
 /// but filters out empty tests.
 pub fn parse_cargo_test<'s>(stderr: &'s str, stdout: &'s str) -> Result<TestRunners<'s>> {
-    use TestType::*;
-
     let mut pkg = None;
     Ok(TestRunners::new(
         parse_cargo_test_with_empty_ones(stderr, stdout)?
             .filter_map(|(runner, info)| {
-                match runner.ty {
-                    UnitLib | UnitBin => pkg = Some(runner.src.bin_name),
-                    Doc => pkg = Some("Doc Tests"),
-                    _ => (),
+                if let Some(name) = pkg_name(&runner) {
+                    pkg = Some(name);
                 }
                 if info.stats.total == 0 {
                     // don't show test types that have no tests
@@ -31,21 +36,73 @@ pub fn parse_cargo_test<'s>(stderr: &'s str, stdout: &'s str) -> Result<TestRunn
     ))
 }
 
+/// Split the output of `cargo test` where stderr and stdout are interleaved into a single
+/// stream (like `cargo test 2>&1 | tee log.txt`) back into `(stderr, stdout)`.
+///
+/// Lines written by cargo itself (see `re().cargo`) are considered from stderr, as well as
+/// the source snippets of compiler diagnostics (see `re().diagnostic`) from the header of the
+/// diagnostic until a blank line, and the rest from stdout. ANSI escapes and `\r` from CRLF line breaks are removed.
+///
+/// Blocks like `---- name stdout ----` in the failures section are kept in stdout, since
+/// doc tests failing to compile print compiler errors there.
+pub fn split_combined_output(text: &str) -> (String, String) {
+    let text = strip_ansi_escapes::strip(text);
+    let text = String::from_utf8_lossy(&text);
+    let (mut stderr, mut stdout) = (String::new(), String::new());
+    let (mut in_block, mut in_diagnostic) = (false, false);
+    for line in text.lines() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if re().stdout_header.is_match(line) {
+            in_block = true;
+        } else if matches!(line, "failures:" | "successes:") || line.starts_with("test result: ") {
+            in_block = false;
+        }
+        let from_cargo = !in_block && re().cargo.is_match(line);
+        if from_cargo && (line.starts_with("error") || line.starts_with("warning")) {
+            in_diagnostic = true;
+        } else if in_block || line.is_empty() {
+            in_diagnostic = false;
+        }
+        let dest = if from_cargo || in_diagnostic && re().diagnostic.is_match(line) {
+            &mut stderr
+        } else {
+            &mut stdout
+        };
+        dest.push_str(line);
+        dest.push('\n');
+    }
+    (stderr, stdout)
+}
+
+/// The pkg name that the test runner starts, i.e. the binary name for unittests
+/// and the presumed Doc pkg for doc tests.
+///
+/// None means the test runner belongs to the pkg of the previous one.
+pub fn pkg_name<'s>(runner: &TestRunner<'s>) -> Option<Text<'s>> {
+    match runner.ty {
+        TestType::UnitLib | TestType::UnitBin => Some(runner.src.bin_name),
+        TestType::Doc => Some("Doc Tests"),
+        _ => None,
+    }
+}
+
 /// The core parsing function that extracts all the information from `cargo test`.
 pub fn parse_cargo_test_with_empty_ones<'s>(
     stderr: &'s str,
     stdout: &'s str,
 ) -> Result<impl Iterator<Item = (TestRunner<'s>, TestInfo<'s>)>> {
     let parsed_stderr = parse_stderr(stderr)?;
+    if parsed_stderr.is_empty() {
+        return Err(Error::NoTestRunner);
+    }
     let parsed_stdout = parse_stdout(stdout)?;
     let err_len = parsed_stderr.len();
     let out_len = parsed_stdout.len();
     if err_len != out_len {
-        return Err(format!(
-            "{err_len} (the amount of test runners from stderr) should \
-         equal to {out_len} (that from stdout)\n\
-         stderr = {stderr:?}\nstdout = {stdout:?}"
-        ));
+        return Err(Error::RunnerCountMismatch {
+            stderr: err_len,
+            stdout: out_len,
+        });
     }
     Ok(parsed_stderr.into_iter().zip(parsed_stdout))
 }
@@ -63,6 +120,72 @@ pub struct TestRunners<'s> {
 }
 
 impl<'s> TestRunners<'s> {
+    /// The `n` slowest tests across all the packages as `(pkg, runner, test)`,
+    /// slowest first. Tests without a reported duration are skipped.
+    pub fn slowest(&self, n: usize) -> Vec<(Pkg<'s>, &TestRunner<'s>, &TestCase<'s>)> {
+        let mut tests: Vec<_> = self
+            .pkgs
+            .iter()
+            .flat_map(|(pkg, pkg_test)| {
+                pkg_test.inner.iter().flat_map(move |data| {
+                    data.info
+                        .parsed
+                        .tests
+                        .iter()
+                        .filter(|test| test.duration.is_some())
+                        .map(move |test| (*pkg, &data.runner, test))
+                })
+            })
+            .collect();
+        tests.sort_by_key(|(_, _, test)| Reverse(test.duration));
+        tests.truncate(n);
+        tests
+    }
+
+    /// Statistics summed up from all the packages.
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        for pkg in self.pkgs.values() {
+            stats += &pkg.stats;
+        }
+        stats
+    }
+
+    /// Mark failed tests that pass on retry as flaky, and move them from `failed`
+    /// to `flaky` in the statistics.
+    pub fn mark_flaky(&mut self, mut is_flaky: impl FnMut(&TestRunner<'s>, &TestCase<'s>) -> bool) {
+        for pkg in self.pkgs.values_mut() {
+            let mut stats = Stats::default();
+            for data in &mut pkg.inner {
+                let mut flaky = 0;
+                for test in &mut data.info.parsed.tests {
+                    if test.status == Status::Failed && is_flaky(&data.runner, test) {
+                        test.status = Status::Flaky;
+                        flaky += 1;
+                    }
+                }
+                let info = &mut data.info.stats;
+                if flaky != 0 {
+                    info.failed -= flaky;
+                    info.flaky += flaky;
+                    info.ok = info.failed == 0;
+                }
+                stats += info;
+            }
+            pkg.stats = stats;
+        }
+    }
+
+    /// Set the target triples of test runners built for one of the triples.
+    /// See [`Src::set_target`].
+    pub fn set_targets(&mut self, triples: &[impl AsRef<str>]) {
+        for pkg in self.pkgs.values_mut() {
+            for data in &mut pkg.inner {
+                data.runner.src.set_target(triples);
+            }
+        }
+    }
+
     pub fn new(v: Vec<(Pkg<'s>, TestRunner<'s>, TestInfo<'s>)>) -> TestRunners<'s> {
         let mut runners = TestRunners::default();
         for (pkg, runner, info) in v {
@@ -79,6 +202,32 @@ impl<'s> TestRunners<'s> {
     }
 }
 
+/// Serialized as `{ "stats": {...}, "pkgs": [{ "name": ..., "stats": {...}, "runners": [...] }] }`
+/// where the aggregate `stats` are summed up from all the packages.
+#[cfg(feature = "serde")]
+impl serde::Serialize for TestRunners<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        #[derive(serde::Serialize)]
+        struct Pkg<'a, 's> {
+            name: &'a Option<Text<'s>>,
+            #[serde(flatten)]
+            test: &'a PkgTest<'s>,
+        }
+
+        let pkgs: Vec<_> = self
+            .pkgs
+            .iter()
+            .map(|(name, test)| Pkg { name, test })
+            .collect();
+        let mut s = serializer.serialize_struct("TestRunners", 2)?;
+        s.serialize_field("stats", &self.stats())?;
+        s.serialize_field("pkgs", &pkgs)?;
+        s.end()
+    }
+}
+
 /// The raw output from `cargo test`.
 pub type Text<'s> = &'s str;
 
@@ -86,7 +235,9 @@ pub type Text<'s> = &'s str;
 /// For doc test type, tests from multiple crates are considered
 /// to be under a presumed Doc pkg.
 #[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PkgTest<'s> {
+    #[cfg_attr(feature = "serde", serde(rename = "runners"))]
     pub inner: Vec<Data<'s>>,
     pub stats: Stats,
 }
@@ -107,13 +258,17 @@ impl<'s> PkgTest<'s> {
 
 /// Information extracted from stdout & stderr.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Data<'s> {
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub runner: TestRunner<'s>,
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub info: TestInfo<'s>,
 }
 
 /// A test runner determined by the type and binary & source path.
 #[derive(Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TestRunner<'s> {
     pub ty: TestType,
     pub src: Src<'s>,
@@ -121,15 +276,19 @@ pub struct TestRunner<'s> {
 
 /// All the information reported by a test runner.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TestInfo<'s> {
     /// Raw test information from stdout.
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub raw: Text<'s>,
     pub stats: Stats,
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub parsed: ParsedCargoTestOutput<'s>,
 }
 
 /// Types of a test.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum TestType {
     UnitLib,
     UnitBin,
@@ -141,6 +300,7 @@ pub enum TestType {
 
 /// Source location and binary name for a test runner.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Src<'s> {
     /// Path of source code (except Doc type) which is relative to its crate
     /// rather than root of project.
@@ -152,21 +312,61 @@ pub struct Src<'s> {
     /// But this field doesn't contain neither the `target/...` prefix nor hash postfix,
     /// so it's possible to see same name from different crates.
     pub bin_name: Text<'s>,
+    /// Path of the test runner binary. None for doc tests.
+    pub bin_path: Option<Text<'s>>,
+    /// The target triple like `x86_64-unknown-linux-musl` if `--target` is used,
+    /// which is only known after [`Src::set_target`]. None for doc tests.
+    pub target: Option<Text<'s>>,
+    /// The profile directory like `debug`, `release` or a custom profile.
+    /// None for doc tests.
+    pub profile: Option<Text<'s>>,
+}
+
+impl Src<'_> {
+    /// Set the target triple if the binary is in the directory of one of the triples,
+    /// like `target/<triple>/debug/deps`, which is where `cargo test --target <triple>`
+    /// puts it.
+    pub fn set_target(&mut self, triples: &[impl AsRef<str>]) {
+        self.target = self
+            .bin_path
+            .and_then(|path| dirs_above_deps(path).nth(1))
+            .filter(|dir| triples.iter().any(|triple| triple.as_ref() == *dir));
+    }
 }
 
 /// Statistics of test.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Stats {
     pub ok: bool,
     pub total: u32,
     pub passed: u32,
     pub failed: u32,
+    /// Tests that failed but passed on retry with `--retries`, which aren't counted in `failed`.
+    pub flaky: u32,
     pub ignored: u32,
     pub measured: u32,
     pub filtered_out: u32,
+    /// Serialized as seconds in float.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_secs"))]
     pub finished_in: Duration,
 }
 
+#[cfg(feature = "serde")]
+#[allow(clippy::trivially_copy_pass_by_ref)]
+pub(crate) fn serialize_secs<S: serde::Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
+}
+
+#[cfg(feature = "serde")]
+#[allow(clippy::ref_option)]
+fn serialize_opt_secs<S: serde::Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    match d {
+        Some(d) => s.serialize_some(&d.as_secs_f64()),
+        None => s.serialize_none(),
+    }
+}
+
 /// Summary text on the bottom.
 impl std::fmt::Display for Stats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -175,6 +375,7 @@ impl std::fmt::Display for Stats {
             total,
             passed,
             failed,
+            flaky,
             ignored,
             measured,
             filtered_out,
@@ -186,10 +387,16 @@ impl std::fmt::Display for Stats {
         } else {
             format!("{failed} failed").red().bold().to_string()
         };
+        // flaky tests only show up with `--retries`
+        let flaky = if flaky == 0 {
+            String::new()
+        } else {
+            format!("{} ", format!("{flaky} flaky;").yellow().bold())
+        };
         write!(
             f,
             "Status: {}; total {total} tests in {time:.2}s: \
-            {passed} passed; {fail}; {ignored} ignored; \
+            {passed} passed; {fail}; {flaky}{ignored} ignored; \
             {measured} measured; {filtered_out} filtered out",
             status(ok)
         )
@@ -208,30 +415,42 @@ impl Stats {
     /// Summary text at the end of root node.
     /// If the metric is zero, it won't be shown.
     pub fn inlay_summary_string(&self) -> String {
+        let time = self.finished_in.as_secs_f32();
+        format!(
+            "{} tests in {time:.2}s: {}",
+            self.total,
+            self.metrics_string()
+        )
+    }
+
+    /// Non-zero numbers of tests in each status, like `✅ 1; ❌ 2; 🔕 1`.
+    pub fn metrics_string(&self) -> String {
         let Stats {
-            total,
             passed,
             failed,
+            flaky,
             ignored,
             filtered_out,
-            finished_in,
             ..
         } = *self;
-        let time = finished_in.as_secs_f32();
+        let theme = theme();
         let mut metrics = Vec::with_capacity(4);
         if passed != 0 {
-            metrics.push(format!("✅ {passed}"));
-        };
+            metrics.push(format!("{} {passed}", theme.passed));
+        }
         if failed != 0 {
-            metrics.push(format!("❌ {failed}").red().to_string());
-        };
+            metrics.push(format!("{} {failed}", theme.failed).red().to_string());
+        }
+        if flaky != 0 {
+            metrics.push(format!("{} {flaky}", theme.flaky).yellow().to_string());
+        }
         if ignored != 0 {
-            metrics.push(format!("🔕 {ignored}"));
-        };
+            metrics.push(format!("{} {ignored}", theme.ignored));
+        }
         if filtered_out != 0 {
-            metrics.push(format!("✂️ {filtered_out}"));
-        };
-        format!("{total} tests in {time:.2}s: {}", metrics.join("; "))
+            metrics.push(format!("{} {filtered_out}", theme.filtered_out));
+        }
+        metrics.join("; ")
     }
 
     /// Root of test tree node depending on the test type.
@@ -263,6 +482,7 @@ impl Default for Stats {
             total: 0,
             passed: 0,
             failed: 0,
+            flaky: 0,
             ignored: 0,
             measured: 0,
             filtered_out: 0,
@@ -280,6 +500,7 @@ impl std::ops::Add<&Stats> for &Stats {
             total: self.total + rhs.total,
             passed: self.passed + rhs.passed,
             failed: self.failed + rhs.failed,
+            flaky: self.flaky + rhs.flaky,
             ignored: self.ignored + rhs.ignored,
             measured: self.measured + rhs.measured,
             filtered_out: self.filtered_out + rhs.filtered_out,
@@ -296,14 +517,85 @@ impl std::ops::AddAssign<&Stats> for Stats {
 
 /// Output from one test runner.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ParsedCargoTestOutput<'s> {
     pub head: Text<'s>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub tree: Vec<Text<'s>>,
+    /// Tests parsed from each line in `tree`, in the same order.
+    pub tests: Vec<TestCase<'s>>,
     pub detail: Text<'s>,
 }
 
-pub fn parse_stderr(stderr: &str) -> Result<Vec<TestRunner>> {
+/// A single test parsed from a line of the test tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TestCase<'s> {
+    /// The whole line, e.g. `test submod::ignore ... ignored, reason`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw: Text<'s>,
+    /// Full name of the test as libtest knows it, e.g. `submod::ignore`
+    /// or `src/doc.rs - doc (line 3)` for doc tests.
+    pub name: Text<'s>,
+    pub status: Status,
+    /// The reason given in `#[ignore = "reason"]` if any.
+    pub ignore_reason: Option<Text<'s>>,
+    /// Marked with `#[should_panic]`.
+    pub should_panic: bool,
+    /// Line number of the code block for doc tests.
+    pub doc_line: Option<u32>,
+    /// Execution time reported by `--report-time` or JSON events.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_opt_secs"))]
+    pub duration: Option<Duration>,
+    /// Details parsed from the failures section if the test failed.
+    pub failure: Option<Failure<'s>>,
+    /// Output printed by the test if it passed, from the successes section of
+    /// `--show-output` or `--nocapture`.
+    pub output: Option<Text<'s>>,
+    /// How the test changed since the run compared with `--compare`.
+    pub change: Option<Change>,
+}
+
+impl<'s> TestCase<'s> {
+    /// Parse a line like `test submod::normal_test ... ok`.
+    pub fn parse(line: Text<'s>) -> Option<TestCase<'s>> {
+        let cap = re().tree.captures(line)?;
+        let split = cap.name("split")?.as_str();
+        let status = cap.name("status")?.as_str();
+        // ` - should panic` and ` - compile( fail)` are appended by libtest, not part of the name
+        let suffix = cap.name("should_panic").or_else(|| cap.name("mode"));
+        Some(TestCase {
+            raw: line,
+            name: &split[..split.len() - suffix.map_or(0, |m| m.len())],
+            status: Status::new(status),
+            ignore_reason: status.strip_prefix("ignored, "),
+            should_panic: cap.name("should_panic").is_some(),
+            doc_line: cap.name("line").and_then(|m| m.as_str().parse().ok()),
+            duration: cap
+                .name("time")
+                .and_then(|m| m.as_str().parse().ok())
+                .map(Duration::from_secs_f64),
+            failure: None,
+            output: None,
+            change: None,
+        })
+    }
+
+    /// The name with the suffix appended by libtest, like `submod::panic - should panic`,
+    /// which is shown in the test tree.
+    pub fn label(&self) -> Text<'s> {
+        self.raw
+            .strip_prefix("test ")
+            .and_then(|rest| rest.split_once(" ... "))
+            .map_or(self.name, |(label, _)| label)
+    }
+}
+
+pub fn parse_stderr(stderr: &str) -> Result<Vec<TestRunner<'_>>> {
     fn parse_stderr_inner<'s>(cap: &regex_lite::Captures<'s>) -> Result<TestRunner<'s>> {
+        let (offset, line) = cap
+            .get(0)
+            .map_or((0, ""), |m| (m.start(), m.as_str().trim()));
         if let Some((path, pkg)) = cap.name("path").zip(cap.name("pkg")) {
             let path = path.as_str();
             let path_norm = Path::new(path);
@@ -319,53 +611,43 @@ pub fn parse_stderr(stderr: &str) -> Result<Vec<TestRunner>> {
                     TestType::UnitBin
                 }
             } else {
-                let Some(base_dir) = path_norm
+                let base_dir = path_norm
                     .components()
                     .next()
-                    .and_then(|p| p.as_os_str().to_str())
-                else {
-                    return Err(format!("failed to parse the type of test: {path:?}"));
-                };
+                    .and_then(|p| p.as_os_str().to_str());
                 match base_dir {
-                    "tests" => TestType::Tests,
-                    "examples" => TestType::Examples,
-                    "benches" => TestType::Benches,
-                    _ => return Err(format!("failed to parse the type of test: {path:?}")),
+                    Some("tests") => TestType::Tests,
+                    Some("examples") => TestType::Examples,
+                    Some("benches") => TestType::Benches,
+                    _ => {
+                        return Err(Error::UnknownTestType {
+                            offset,
+                            line: line.to_owned(),
+                        })
+                    }
                 }
             };
-
-            // e.g. target/debug/deps/cargo_pretty_test-xxxxxxxxxxxxxxxx
-            let mut pkg_comp = Path::new(pkg.as_str()).components();
-            match pkg_comp.next().map(|p| p.as_os_str() == "target") {
-                Some(true) => (),
-                _ => return Err(format!("failed to parse the location of test: {pkg:?}")),
-            }
-            let pkg = pkg_comp
-                .nth(2)
-                .ok_or_else(|| format!("can't get the third component in {pkg:?}"))?
-                .as_os_str()
-                .to_str()
-                .ok_or_else(|| format!("can't turn os_str into str in {pkg:?}"))?;
-            let pkg = &pkg[..pkg
-                .find('-')
-                .ok_or_else(|| format!("pkg `{pkg}` should be of `pkgname-hash` pattern"))?];
-            Ok(TestRunner {
-                ty,
-                src: Src {
-                    src_path: path,
-                    bin_name: pkg,
-                },
-            })
+            let src = parse_bin_path(path, pkg.as_str()).ok_or_else(|| Error::InvalidBinPath {
+                offset,
+                line: line.to_owned(),
+            })?;
+            Ok(TestRunner { ty, src })
         } else if let Some(s) = cap.name("doc").map(|m| m.as_str()) {
             Ok(TestRunner {
                 ty: TestType::Doc,
                 src: Src {
                     src_path: s,
                     bin_name: s,
+                    bin_path: None,
+                    target: None,
+                    profile: None,
                 },
             })
         } else {
-            Err(format!("{cap:?} is not supported to be parsed"))
+            Err(Error::UnknownTestType {
+                offset,
+                line: line.to_owned(),
+            })
         }
     }
     re().ty
@@ -374,76 +656,94 @@ pub fn parse_stderr(stderr: &str) -> Result<Vec<TestRunner>> {
         .collect::<Result<Vec<_>>>()
 }
 
+/// Parse the path to a test binary, which is `<target-dir>/[<triple>/]<profile>/deps/<name>-<hash>`:
+/// * `target/debug/deps/cargo_pretty_test-9b4400a4dee777d5`
+/// * `/tmp/tgt/x86_64-unknown-linux-musl/release/deps/cargo_pretty_test-9b4400a4dee777d5`
+/// * `target\\custom\\deps\\cargo_pretty_test-9b4400a4dee777d5.exe`
+///
+/// The target triple can't be told from a target dir by the path alone, so it's left `None`
+/// to be set by [`Src::set_target`].
+fn parse_bin_path<'s>(src_path: Text<'s>, path: &'s str) -> Option<Src<'s>> {
+    let file = path.rsplit(['/', '\\']).find(|c| !c.is_empty())?;
+    let profile = dirs_above_deps(path).next()?;
+    let file = file.strip_suffix(".exe").unwrap_or(file);
+    // the file name is `pkgname-hash`
+    let bin_name = &file[..file.rfind('-')?];
+    Some(Src {
+        src_path,
+        bin_name,
+        bin_path: Some(path),
+        target: None,
+        profile: Some(profile),
+    })
+}
+
+/// Directories above the `deps` or `build` directory of the binary, from the nearest one,
+/// i.e. the profile dir, followed by the triple dir or the target dir. It's empty if there's
+/// no such directory.
+fn dirs_above_deps(path: &str) -> impl Iterator<Item = &str> {
+    let mut rev = path.rsplit(['/', '\\']).filter(|c| !c.is_empty()).skip(1);
+    // exhausts the iterator if not found
+    _ = rev.by_ref().find(|dir| matches!(*dir, "deps" | "build"));
+    rev
+}
+
 #[allow(clippy::too_many_lines)]
-pub fn parse_stdout(stdout: &str) -> Result<Vec<TestInfo>> {
-    fn parse_stdout_except_head(raw: &str) -> Result<(Vec<Text>, Text, Stats, Text)> {
-        fn parse_tree_detail(text: &str) -> (Vec<Text>, Text) {
+pub fn parse_stdout(stdout: &str) -> Result<Vec<TestInfo<'_>>> {
+    /// Parse the output of a test runner, where `offset` and `head` locate its
+    /// `running N tests` line in stdout for errors.
+    fn parse_stdout_except_head<'s>(
+        raw: &'s str,
+        offset: usize,
+        head: &str,
+    ) -> Result<(Vec<Text<'s>>, Text<'s>, Stats, Text<'s>)> {
+        fn parse_tree_detail(text: &str) -> (Vec<Text<'_>>, usize) {
             let line: Vec<_> = re().tree.find_iter(text).collect();
             let tree_end = line.last().map_or(0, |cap| cap.end() + 1);
-            let mut tree: Vec<_> = line.into_iter().map(|cap| cap.as_str()).collect();
-            tree.sort_unstable();
-            (tree, text[tree_end..].trim())
+            let tree: Vec<_> = line.into_iter().map(|cap| cap.as_str()).collect();
+            (tree, tree_end.min(text.len()))
         }
 
-        if raw.is_empty() {
-            Err("raw stdout is empty".into())
-        } else {
-            let (tree, detail) = parse_tree_detail(raw);
-            let cap = re()
-                .stats
-                .captures(detail)
-                .ok_or_else(|| format!("`stats` is not found in {raw:?}"))?;
-            let stats = Stats {
-                ok: cap
-                    .name("ok")
-                    .ok_or_else(|| format!("`ok` is not found in {raw:?}"))?
-                    .as_str()
-                    == "ok",
-                total: u32::try_from(tree.len()).map_err(|err| err.to_string())?,
-                passed: cap
-                    .name("passed")
-                    .ok_or_else(|| format!("`passed` is not found in {raw:?}"))?
-                    .as_str()
-                    .parse::<u32>()
-                    .map_err(|err| err.to_string())?,
-                failed: cap
-                    .name("failed")
-                    .ok_or_else(|| format!("`failed` is not found in {raw:?}"))?
-                    .as_str()
-                    .parse::<u32>()
-                    .map_err(|err| err.to_string())?,
-                ignored: cap
-                    .name("ignored")
-                    .ok_or_else(|| format!("`ignored` is not found in {raw:?}"))?
-                    .as_str()
-                    .parse::<u32>()
-                    .map_err(|err| err.to_string())?,
-                measured: cap
-                    .name("measured")
-                    .ok_or_else(|| format!("`measured` is not found in {raw:?}"))?
-                    .as_str()
-                    .parse::<u32>()
-                    .map_err(|err| err.to_string())?,
-                filtered_out: cap
-                    .name("filtered")
-                    .ok_or_else(|| format!("`filtered` is not found in {raw:?}"))?
-                    .as_str()
-                    .parse::<u32>()
-                    .map_err(|err| err.to_string())?,
-                finished_in: Duration::from_secs_f32(
-                    cap.name("time")
-                        .ok_or_else(|| format!("`time` is not found in {raw:?}"))?
-                        .as_str()
-                        .parse::<f32>()
-                        .map_err(|err| err.to_string())?,
-                ),
-            };
-            let stats_start = cap
-                .get(0)
-                .ok_or_else(|| format!("can't get stats start in {raw:?}"))?
-                .start();
-            Ok((tree, detail[..stats_start].trim(), stats, raw))
-        }
+        let (tree, tree_end) = parse_tree_detail(raw);
+        let detail = raw[tree_end..].trim();
+        let detail_start = raw.len() - raw[tree_end..].trim_start().len();
+        let Some(cap) = re().stats.captures(detail) else {
+            return Err(Error::MissingStats {
+                offset,
+                line: head.to_owned(),
+            });
+        };
+        let (stats_start, stats_line) = cap.get(0).map_or((0, ""), |m| (m.start(), m.as_str()));
+        let invalid = |reason: String| Error::InvalidStats {
+            offset: offset + detail_start + stats_start,
+            line: stats_line.to_owned(),
+            reason,
+        };
+        let number = |name: &str| -> Result<u32> {
+            cap.name(name)
+                .map_or("", |m| m.as_str())
+                .parse()
+                .map_err(|err| invalid(format!("`{name}`: {err}")))
+        };
+        let time = cap
+            .name("time")
+            .map_or("", |m| m.as_str())
+            .parse::<f64>()
+            .ok()
+            .and_then(|t| Duration::try_from_secs_f64(t).ok())
+            .ok_or_else(|| invalid("invalid time".into()))?;
+        let stats = Stats {
+            ok: cap.name("ok").is_some_and(|m| m.as_str() == "ok"),
+            total: u32::try_from(tree.len()).map_err(|err| invalid(err.to_string()))?,
+            passed: number("passed")?,
+            failed: number("failed")?,
+            flaky: 0,
+            ignored: number("ignored")?,
+            measured: number("measured")?,
+            filtered_out: number("filtered")?,
+            finished_in: time,
+        };
+        Ok((tree, detail[..stats_start].trim(), stats, raw))
     }
 
     let split: Vec<_> = re()
@@ -459,46 +759,64 @@ pub fn parse_stdout(stdout: &str) -> Result<Vec<TestInfo>> {
         })
         .collect();
     if split.is_empty() {
-        return Err(format!(
-            "{stdout:?} should contain `running (?P<amount>\\d+) tests?` pattern"
-        ));
+        return Err(Error::NoTestRunning);
     }
-    let parsed_stdout = if split.len() == 1 {
-        vec![parse_stdout_except_head(stdout)?]
-    } else {
-        let start = split.iter().map(|v| v.0);
-        let end = start.clone().skip(1).chain([stdout.len()]);
-        start
-            .zip(end)
-            .map(|(a, b)| {
-                let src = &stdout[a..b];
-                parse_stdout_except_head(src)
-            })
-            .collect::<Result<Vec<_>>>()?
-    };
+    // each test runner spans from its `running N tests` line to the next one
+    let start = split.iter().map(|v| v.0);
+    let end = start.clone().skip(1).chain([stdout.len()]);
+    let parsed_stdout = start
+        .zip(end)
+        .zip(&split)
+        .map(|((a, b), head)| parse_stdout_except_head(&stdout[a..b], a, head.1))
+        .collect::<Result<Vec<_>>>()?;
 
     // check the amount of tests
-    let parsed_amount_from_head: Vec<_> = split.iter().map(|v| v.2).collect();
-    let stats_total: Vec<_> = parsed_stdout.iter().map(|v| v.2.total).collect();
-    if parsed_amount_from_head != stats_total {
-        return Err(format!(
-            "the parsed amount of running tests {parsed_amount_from_head:?} \
-             should equal to the number in stats.total {stats_total:?}\n\
-             split = {split:#?}\nparsed_stdout = {parsed_stdout:#?}"
-        ));
+    for (head, parsed) in split.iter().zip(&parsed_stdout) {
+        if head.2 != parsed.2.total {
+            return Err(Error::TotalMismatch {
+                offset: head.0,
+                line: head.1.to_owned(),
+                head: head.2,
+                parsed: parsed.2.total,
+            });
+        }
     }
 
     Ok(split
         .iter()
         .zip(parsed_stdout)
-        .map(|(head_info, v)| TestInfo {
-            parsed: ParsedCargoTestOutput {
-                head: head_info.1,
-                tree: v.0,
-                detail: v.1,
-            },
-            stats: v.2,
-            raw: v.3,
+        .map(|(head_info, v)| {
+            let mut tests: Vec<_> =
+                v.0.iter()
+                    .filter_map(|line| TestCase::parse(line))
+                    .collect();
+            let (successes, detail) = split_successes(v.1);
+            for (name, output) in parse_successes(successes) {
+                if let Some(test) = tests
+                    .iter_mut()
+                    .find(|t| t.status == Status::Ok && t.name == name)
+                {
+                    test.output = Some(output);
+                }
+            }
+            for failure in parse_failures(detail) {
+                if let Some(test) = tests
+                    .iter_mut()
+                    .find(|t| t.status == Status::Failed && t.name == failure.name)
+                {
+                    test.failure = Some(failure);
+                }
+            }
+            TestInfo {
+                parsed: ParsedCargoTestOutput {
+                    head: head_info.1,
+                    tests,
+                    tree: v.0,
+                    detail,
+                },
+                stats: v.2,
+                raw: v.3,
+            }
         })
         .collect())
 }
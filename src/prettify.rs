@@ -1,93 +1,478 @@
-use crate::regex::re;
-use colored::Colorize;
-use std::{
-    borrow::Cow,
-    collections::{btree_map::Entry, BTreeMap},
+use crate::{
+    failure::Failure,
+    history::Change,
+    parsing::{Data, Stats, TestCase, Text},
+    theme::theme,
 };
+use colored::Colorize;
+use indexmap::{map::Entry, IndexMap};
+use std::{borrow::Cow, cmp::Reverse, time::Duration};
 use termtree::{GlyphPalette, Tree};
 
 pub type TestTree<'s> = Tree<Cow<'s, str>>;
 
-/// Make the cargo test output pretty.
+/// Options to render the test tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// Show the panic message and location of each failed test as its child leaves.
+    pub inline_failures: bool,
+    /// Max lines of the panic message shown for each failed test, or of the output shown
+    /// for each passed test with `--show-output` or `--nocapture`. 0 means no limit.
+    pub failure_lines: usize,
+    /// Tests to show in the tree. Modules, runners and packages without any of them are dropped.
+    pub statuses: StatusFilter,
+    /// Show a module or runner whose tests all passed as a single line.
+    pub collapse_passing: bool,
+    /// Show the numbers of tests in each status beside modules.
+    pub module_counts: bool,
+    /// Order of tests and modules under each module.
+    pub sort: Sort,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            inline_failures: false,
+            failure_lines: 5,
+            statuses: StatusFilter::ALL,
+            collapse_passing: false,
+            module_counts: false,
+            sort: Sort::Name,
+        }
+    }
+}
+
+/// Order of tests and modules under each module, like `--sort status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sort {
+    /// Alphabetic.
+    #[default]
+    Name,
+    /// Failed, flaky, passed and then ignored, where a module is in the most notable status
+    /// of its tests. Ties are sorted by name.
+    Status,
+    /// Slowest first, where a module takes the total duration of its tests. Those without
+    /// durations come last by name.
+    Duration,
+    /// The order reported by libtest, which is the order of finishing when tests run
+    /// in parallel.
+    Declaration,
+}
+
+impl std::str::FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(Sort::Name),
+            "status" => Ok(Sort::Status),
+            "duration" => Ok(Sort::Duration),
+            "declaration" => Ok(Sort::Declaration),
+            _ => Err(format!(
+                "unknown order `{s}`, expected one of name, status, duration, declaration"
+            )),
+        }
+    }
+}
+
+/// A set of statuses, like `--only failed,ignored`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusFilter(u8);
+
+impl StatusFilter {
+    pub const ALL: StatusFilter = StatusFilter(0b1111);
+    pub const NONE: StatusFilter = StatusFilter(0);
+
+    const fn bit(status: Status) -> u8 {
+        1 << status as u8
+    }
+
+    #[must_use]
+    pub const fn with(self, status: Status) -> StatusFilter {
+        StatusFilter(self.0 | Self::bit(status))
+    }
+
+    #[must_use]
+    pub const fn without(self, status: Status) -> StatusFilter {
+        StatusFilter(self.0 & !Self::bit(status))
+    }
+
+    pub const fn contains(self, status: Status) -> bool {
+        self.0 & Self::bit(status) != 0
+    }
+
+    pub const fn is_all(self) -> bool {
+        self.0 == Self::ALL.0
+    }
+
+    /// Parse comma-separated statuses like `failed,ignored`, where `passed` is also accepted
+    /// for `ok`.
+    pub fn parse(list: &str) -> Result<StatusFilter, String> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .try_fold(StatusFilter::NONE, |filter, s| {
+                let status = match s {
+                    "ok" | "passed" => Status::Ok,
+                    "failed" => Status::Failed,
+                    "ignored" => Status::Ignored,
+                    "flaky" => Status::Flaky,
+                    _ => {
+                        return Err(format!(
+                            "unknown status `{s}`, expected one of passed, failed, ignored, flaky"
+                        ))
+                    }
+                };
+                Ok(filter.with(status))
+            })
+    }
+}
+
+/// Make the test tree from parsed tests, with failure details or output under them
+/// depending on the options.
 #[must_use]
-pub fn make_pretty<'s, S>(root: S, lines: impl Iterator<Item = &'s str>) -> Option<TestTree<'s>>
+pub fn make_pretty<'s, S>(root: S, tests: &[TestCase<'s>], opts: &Options) -> TestTree<'s>
 where
     S: Into<Cow<'s, str>>,
 {
-    let mut path = BTreeMap::new();
+    build_tree(root.into(), Module::new(tests, opts), opts)
+}
+
+/// Build the test tree for a test runner under the root, or `None` if none of its tests
+/// are shown.
+pub fn runner_tree<'s>(
+    root: impl Into<Cow<'s, str>>,
+    data: &Data<'s>,
+    opts: &Options,
+) -> Option<TestTree<'s>> {
+    let parsed = &data.info.parsed;
+    let tree = make_pretty(root, &parsed.tests, opts);
+    // drop runners without shown tests, which can't be told by leaves with --collapse-passing
+    let shown = || {
+        parsed
+            .tests
+            .iter()
+            .any(|t| opts.statuses.contains(t.status))
+    };
+    (opts.statuses.is_all() || shown()).then_some(tree)
+}
+
+fn build_tree<'s>(root: Cow<'s, str>, path: Module<'s>, opts: &Options) -> TestTree<'s> {
+    let mut tree = Tree::new(root);
+    // the root of a runner already shows the summary
+    if opts.collapse_passing && path.stats.total != 0 && path.all_passed() {
+        return tree;
+    }
+    for (name, child) in sorted(path.children, opts.sort) {
+        make_tree(name, child, &mut tree, opts);
+    }
+    tree
+}
+
+/// Lines of the panic message and the location of a failed test.
+fn failure_leaves<'s>(failure: &Failure<'s>, max_lines: usize) -> Vec<Cow<'s, str>> {
+    let lines = failure
+        .message
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty());
+    let mut leaves = truncated_leaves(lines, max_lines, |line| line.red().to_string().into());
+    if let Some(location) = failure.location {
+        leaves.push(format!("at {location}").bright_black().to_string().into());
+    }
+    leaves
+}
+
+/// Lines of the output printed by a passed test.
+fn output_leaves(output: &str, max_lines: usize) -> Vec<Cow<'_, str>> {
+    let lines = output.lines().map(str::trim_end);
+    truncated_leaves(lines, max_lines, Cow::from)
+}
+
+/// Leaves of at most `max_lines` lines, followed by the number of omitted ones if any.
+/// 0 means no limit.
+fn truncated_leaves<'s>(
+    lines: impl Iterator<Item = &'s str>,
+    max_lines: usize,
+    leaf: impl Fn(&'s str) -> Cow<'s, str>,
+) -> Vec<Cow<'s, str>> {
+    let max_lines = if max_lines == 0 {
+        usize::MAX
+    } else {
+        max_lines
+    };
+    let mut leaves = Vec::new();
+    let mut omitted = 0;
     for line in lines {
-        let cap = re().tree.captures(line)?;
-        let mut split = cap.name("split")?.as_str().split("::");
-        let status = cap.name("status")?.as_str();
-        let next = split.next();
-        make_node(split, status, &mut path, next);
+        if leaves.len() < max_lines {
+            leaves.push(leaf(line));
+        } else {
+            omitted += 1;
+        }
     }
-    let mut tree = Tree::new(root.into());
-    for (name, child) in path {
-        make_tree(name, &child, &mut tree);
+    if omitted != 0 {
+        let s = if omitted == 1 { "" } else { "s" };
+        leaves.push(
+            format!("... {omitted} more line{s}")
+                .bright_black()
+                .to_string()
+                .into(),
+        );
     }
-    Some(tree)
+    leaves
 }
 
 #[derive(Debug)]
-enum Node<'s> {
-    Path(BTreeMap<&'s str, Node<'s>>),
-    Status(&'s str),
+pub(crate) enum Node<'s> {
+    Path(Module<'s>),
+    Test(Leaf<'s>),
 }
 
-/// Add paths to Node.
+impl Node<'_> {
+    fn status(&self) -> Status {
+        match self {
+            Node::Path(module) => module.status(),
+            Node::Test(leaf) => leaf.status,
+        }
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        match self {
+            Node::Path(module) => Some(module.stats.finished_in).filter(|d| *d != Duration::ZERO),
+            Node::Test(leaf) => leaf.duration,
+        }
+    }
+}
+
+/// Children of a module in the order to show.
+pub(crate) fn sorted<'s>(
+    children: IndexMap<&'s str, Node<'s>>,
+    sort: Sort,
+) -> Vec<(&'s str, Node<'s>)> {
+    let mut children: Vec<_> = children.into_iter().collect();
+    match sort {
+        Sort::Name => children.sort_by(|a, b| a.0.cmp(b.0)),
+        Sort::Status => children.sort_by_key(|(name, node)| (Reverse(node.status()), *name)),
+        // `Reverse(None)` is greater than `Reverse(Some(_))`, so tests without durations come last
+        Sort::Duration => children.sort_by_key(|(name, node)| (Reverse(node.duration()), *name)),
+        Sort::Declaration => (),
+    }
+    children
+}
+
+/// A module in the tree.
+#[derive(Debug, Default)]
+pub(crate) struct Module<'s> {
+    /// In the order of tests reported by libtest.
+    pub(crate) children: IndexMap<&'s str, Node<'s>>,
+    /// Numbers and the total duration of the tests under it.
+    pub(crate) stats: Stats,
+}
+
+impl<'s> Module<'s> {
+    /// The modules of the tests in the shown statuses, with failure details or output
+    /// under the tests depending on the options.
+    pub(crate) fn new(tests: &[TestCase<'s>], opts: &Options) -> Module<'s> {
+        let mut path = Module::default();
+        for test in tests {
+            if !opts.statuses.contains(test.status) {
+                // modules without shown tests aren't created
+                continue;
+            }
+            let mut split = test.label().split("::");
+            let mut leaf = Leaf::new(test.name, test.status);
+            leaf.duration = test.duration;
+            leaf.change = test.change;
+            if opts.inline_failures {
+                if let Some(failure) = &test.failure {
+                    leaf.children = failure_leaves(failure, opts.failure_lines);
+                }
+            }
+            if let Some(output) = test.output {
+                leaf.children = output_leaves(output, opts.failure_lines);
+            }
+            let next = split.next();
+            make_node(split, leaf, &mut path, next);
+        }
+        path
+    }
+
+    fn count(&mut self, leaf: &Leaf) {
+        let stats = &mut self.stats;
+        stats.total += 1;
+        stats.finished_in += leaf.duration.unwrap_or_default();
+        match leaf.status {
+            Status::Ok => stats.passed += 1,
+            Status::Ignored => stats.ignored += 1,
+            Status::Failed => {
+                stats.failed += 1;
+                stats.ok = false;
+            }
+            Status::Flaky => stats.flaky += 1,
+        }
+    }
+
+    fn all_passed(&self) -> bool {
+        self.stats.total == self.stats.passed
+    }
+
+    /// The most notable status of the tests under it, which colors the module name.
+    pub(crate) fn status(&self) -> Status {
+        let stats = &self.stats;
+        [
+            (Status::Ok, stats.passed),
+            (Status::Failed, stats.failed),
+            (Status::Flaky, stats.flaky),
+        ]
+        .into_iter()
+        .filter(|&(_, count)| count != 0)
+        .map(|(status, _)| status)
+        .max()
+        .unwrap_or(Status::Ignored)
+    }
+}
+
+/// A test in the tree.
+#[derive(Debug)]
+pub(crate) struct Leaf<'s> {
+    /// The full name of the test, like `submod::panic::panicked`, to find the test
+    /// in the terminal UI.
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub(crate) name: Text<'s>,
+    pub(crate) status: Status,
+    /// Shown beside the test name if reported.
+    pub(crate) duration: Option<Duration>,
+    /// Shown beside the test name with `--compare`.
+    change: Option<Change>,
+    /// Extra lines shown under the test.
+    children: Vec<Cow<'s, str>>,
+}
+
+impl<'s> Leaf<'s> {
+    fn new(name: Text<'s>, status: Status) -> Self {
+        Leaf {
+            name,
+            status,
+            duration: None,
+            change: None,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Add paths to Node, and count the test in each module on the way.
 fn make_node<'s>(
     mut split: impl Iterator<Item = &'s str>,
-    status: &'s str,
-    path: &mut BTreeMap<&'s str, Node<'s>>,
+    leaf: Leaf<'s>,
+    path: &mut Module<'s>,
     key: Option<&'s str>,
 ) {
     let Some(key) = key else { return };
+    path.count(&leaf);
     let next = split.next();
-    match path.entry(key) {
+    match path.children.entry(key) {
         Entry::Vacant(empty) => {
             if next.is_some() {
-                let mut btree = BTreeMap::new();
-                make_node(split, status, &mut btree, next);
-                empty.insert(Node::Path(btree));
+                let mut module = Module::default();
+                make_node(split, leaf, &mut module, next);
+                empty.insert(Node::Path(module));
             } else {
-                empty.insert(Node::Status(status));
+                empty.insert(Node::Test(leaf));
             }
         }
         Entry::Occupied(mut node) => {
-            if let Node::Path(btree) = node.get_mut() {
-                make_node(split, status, btree, next);
+            if let Node::Path(module) = node.get_mut() {
+                make_node(split, leaf, module, next);
             }
         }
     }
 }
 
 /// Add Node to Tree.
-fn make_tree<'s>(root: &'s str, node: &Node<'s>, parent: &mut TestTree<'s>) {
+fn make_tree<'s>(root: &'s str, node: Node<'s>, parent: &mut TestTree<'s>, opts: &Options) {
     match node {
-        Node::Path(btree) => {
-            let mut testtree = Tree::new(root.into());
-            for (path, child) in btree {
-                make_tree(path, child, &mut testtree);
+        Node::Path(module) if opts.collapse_passing && module.all_passed() => {
+            let total = module.stats.total;
+            let s = if total == 1 { "" } else { "s" };
+            let line = format!("{root} ({total} test{s})");
+            parent.push(Tree::new(line.into()).with_glyphs(Status::Ok.glyph()));
+        }
+        Node::Path(module) => {
+            let name = if opts.module_counts {
+                let name = module.status().set_color(root);
+                let stats = &module.stats;
+                format!("{name} ({} tests: {})", stats.total, stats.metrics_string()).into()
+            } else {
+                root.into()
+            };
+            let mut testtree = Tree::new(name);
+            for (path, child) in sorted(module.children, opts.sort) {
+                make_tree(path, child, &mut testtree, opts);
             }
             parent.push(testtree);
         }
-        Node::Status(s) => {
-            let status = Status::new(s);
-            let testtree = Tree::new(status.set_color(root));
+        Node::Test(Leaf {
+            status,
+            duration,
+            change,
+            children,
+            ..
+        }) => {
+            let mut name = status.set_color(root);
+            if let Some(d) = duration {
+                name = format!("{name} {}", format_duration(d).bright_black()).into();
+            }
+            if let Some(change) = change {
+                name = format!("{name} {}", change.mark()).into();
+            }
+            let testtree = Tree::new(name).with_leaves(children);
             parent.push(testtree.with_glyphs(status.glyph()));
         }
     }
 }
 
-#[derive(Clone, Copy)]
+/// Format the duration of a single test like `<0.123s>`.
+pub fn format_duration(d: Duration) -> String {
+    format!("<{:.3}s>", d.as_secs_f64())
+}
+
+/// The status of a test.
+///
+/// Statuses are ordered by how notable they are: ignored, ok, flaky and then failed, so
+/// the most notable one of some tests is the max.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Status {
     Ok,
     Ignored,
     Failed,
+    /// Failed but passed on retry with `--retries`.
+    Flaky,
+}
+
+impl Ord for Status {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.rank().cmp(&other.rank())
+    }
+}
+
+impl PartialOrd for Status {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl Status {
+    const fn rank(self) -> u8 {
+        match self {
+            Status::Ignored => 0,
+            Status::Ok => 1,
+            Status::Flaky => 2,
+            Status::Failed => 3,
+        }
+    }
+
     pub fn new(status: &str) -> Status {
         if status.ends_with("ok") {
             // including the case that should panic and did panic
@@ -100,14 +485,27 @@ impl Status {
         }
     }
 
-    pub const fn icon(self) -> &'static str {
+    /// The name of the status, like `failed`.
+    pub const fn name(self) -> &'static str {
         match self {
-            Status::Ok => "─ ✅ ",
-            Status::Ignored => "─ 🔕 ",
-            Status::Failed => "─ ❌ ",
+            Status::Ok => "ok",
+            Status::Ignored => "ignored",
+            Status::Failed => "failed",
+            Status::Flaky => "flaky",
         }
     }
 
+    /// The item indent of a test in the tree with the icon in the theme in use,
+    /// like `─ ✅ `.
+    pub fn icon(self) -> &'static str {
+        theme().indent(self)
+    }
+
+    /// The icon alone in the theme in use, like `✅`.
+    pub fn symbol(self) -> &'static str {
+        theme().icon(self)
+    }
+
     /// Display with a status icon
     pub fn glyph(self) -> GlyphPalette {
         let mut glyph = GlyphPalette::new();
@@ -120,14 +518,18 @@ impl Status {
             Status::Ok => s.into(),
             Status::Ignored => s.bright_black().to_string().into(),
             Status::Failed => s.red().bold().to_string().into(),
+            Status::Flaky => s.yellow().to_string().into(),
         }
     }
 }
 
+/// The notation of the emoji icons, regardless of `--icons`.
+#[deprecated(note = "use theme().notation()")]
 pub const ICON_NOTATION: &str = "
 Icon Notation:
 ─ ✅ pass (including the case that should panic and did panic)
 ─ ❌ fail (including the case that should panic but didn't panic)
+─ 🔁 flaky (failed but passed on retry with --retries)
 ─ 🔕 ignored (with reason omitted)
 ─ ✂️ filtered out (won't show in the test tree, but will be computed in the summary)
 ";
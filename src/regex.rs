@@ -24,6 +24,12 @@ pub struct Re {
     pub head: Regex,
     pub tree: Regex,
     pub stats: Regex,
+    pub cargo: Regex,
+    pub diagnostic: Regex,
+    pub stdout_header: Regex,
+    pub panic: Regex,
+    pub assert_values: Regex,
+    pub compile_error: Regex,
     pub separator: ColoredString,
 }
 
@@ -57,7 +63,10 @@ lazy_static!(pub re, Re, {
         // test tests/integration/src/lib.rs - empty_doc_mod::Item (line 48) ... ok
         // test tests/integration/src/lib.rs - empty_doc_mod::private_mod (line 44) ... ok
         // test tests/integration/src/lib.rs - (line 1) ... ok
-        tree: Regex::new(r"(?m)^test (?P<split>\S+( - should panic)?(?<doctest> -( \S+)? \(line \d+\)( - compile( fail)?)?)?) \.\.\. (?P<status>\S+(, .*)?)$").expect(RE_ERROR),
+        //
+        // With `--report-time` or JSON events, passed and failed tests come with the duration:
+        // test submod::normal_test ... ok <0.001s>
+        tree: Regex::new(r"(?m)^test (?P<split>\S+(?P<should_panic> - should panic)?(?<doctest> -( \S+)? \(line (?P<line>\d+)\)(?P<mode> - compile( fail)?)?)?) \.\.\. (?P<status>\S+(, .*)?)( <(?P<time>\d+(\.\d+)?)s>)?$").expect(RE_ERROR),
         // test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
         stats: Regex::new(r"(?mx)
             ^test\ result:\ (?P<ok>\S+)\.
@@ -67,6 +76,58 @@ lazy_static!(pub re, Re, {
             \ (?P<measured>\d+)\ measured;
             \ (?P<filtered>\d+)\ filtered\ out;
             \ finished\ in\ (?P<time>\S+)s$").expect(RE_ERROR),
+        // Lines written by cargo to stderr:
+        //    Compiling cargo-pretty-test v0.2.5 (/root/cargo-pretty-test)
+        //     Finished test [unoptimized + debuginfo] target(s) in 0.00s
+        //      Running unittests src/lib.rs (target/debug/deps/cargo_pretty_test-9b4400a4dee777d5)
+        //    Doc-tests cargo-pretty-test
+        // warning: unused variable: `x`
+        // error: test failed, to rerun pass `-p integration --lib`
+        // error: 2 targets failed:
+        //     `-p integration --lib`
+        // For more information about this error, try `rustc --explain E0425`.
+        cargo: Regex::new(r"(?mx)
+            ^(\s+(Compiling|Checking|Finished|Running|Doc-tests|Downloading|Downloaded|Updating|Locking|Adding|Blocking|Fresh|Documenting)\ .*
+            |(warning|error)(\[\w+\])?:\ .*
+            |\ {4}`-p\ .*`
+            |(Some\ errors\ have\ detailed\ explanations|For\ more\ information\ about\ (this|an)\ error).*)$").expect(RE_ERROR),
+        // The source snippets below the header of a compiler diagnostic, which may as well
+        // be printed by tests, so they only count after `error[E0425]: ...` or `warning: ...`:
+        //  --> src/lib.rs:1:21
+        //   |
+        // 1 | pub fn f() -> u32 { x }
+        //   = note: `#[warn(unused_variables)]` on by default
+        diagnostic: Regex::new(r"(?x)^(\s*-->\ .*|\s*\d*\ \|.*|\s+=\ .*)$").expect(RE_ERROR),
+        // ---- submod::panic::panicked stdout ----
+        // ---- tests/integration/src/lib.rs - attribute::should_compile_fail_but_didnt (line 96) stdout ----
+        stdout_header: Regex::new(r"^---- (?P<name>.+) stdout ----$").expect(RE_ERROR),
+        // Since Rust 1.73 (the thread id is printed since Rust 1.89):
+        // thread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:
+        // thread 'submod::panic::panicked' (1234) panicked at tests/integration/src/lib.rs:11:13:
+        // Before Rust 1.73 (the message may span multiple lines):
+        // thread 'submod::panic::panicked' panicked at 'explicit panic', tests/integration/src/lib.rs:11:13
+        panic: Regex::new(r"(?mx)
+            ^thread\ '(?P<thread>[^']*)'(\ \(\d+\))?\ panicked\ at\ (
+                '(?s:(?P<old_msg>.*?))',\ (?P<old_loc>\S+:\d+:\d+)
+                |(?P<loc>\S+:\d+:\d+):
+            )$").expect(RE_ERROR),
+        // assertion `left == right` failed
+        //   left: 1
+        //  right: 2
+        // Before Rust 1.73:
+        // assertion failed: `(left == right)`
+        //   left: `1`,
+        //  right: `2`
+        assert_values: Regex::new(r"(?m)^\s*left: (?P<left>.*)\n\s*right: (?P<right>.*)$").expect(RE_ERROR),
+        // error[E0425]: cannot find value `x` in this scope
+        // error: could not compile `integration` (lib test) due to 1 previous error
+        // Before Rust 1.72:
+        // error: could not compile `integration` due to previous error
+        compile_error: Regex::new(r"(?x)
+            ^error(\[(?P<code>\w+)\])?:\ (
+                could\ not\ compile\ `(?P<krate>[^`]+)`(\ \((?P<target>[^)]+)\))?(\ due\ to\ .*)?
+                |(?P<message>.*)
+            )$").expect(RE_ERROR),
         separator: "────────────────────────────────────────────────────────────────────────".yellow().bold()
     }
 });
@@ -0,0 +1,362 @@
+//! Rerun the tests that failed in the last run.
+//!
+//! Each run records its failed tests in `<target-dir>/pretty-test/last-failed`, one test per line
+//! with tab-separated test type, source path, binary name and test name, like
+//! `UnitLib`, `src/lib.rs`, `integration` and `submod::panic::panicked`.
+//!
+//! `--rerun-failed` maps them back to cargo's target selectors with `cargo metadata`, like
+//! `-p integration --lib`, and runs `cargo test` for each target with `--exact` test names.
+
+use crate::{
+    args::cargo_values,
+    parsing::{TestCase, TestRunner, TestRunners, TestType},
+    prettify::Status,
+};
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::{
+    io::{self, Error},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The directory under the target dir where cargo-pretty-test keeps its data.
+pub const DATA_DIR: &str = "pretty-test";
+
+const LAST_FAILED: &str = "last-failed";
+
+/// A failed test from the last run.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FailedTest {
+    pub ty: TestType,
+    pub src_path: String,
+    pub bin_name: String,
+    pub name: String,
+}
+
+/// Packages and the target dir from `cargo metadata`.
+#[derive(Debug, Default)]
+pub struct Metadata {
+    pub target_directory: PathBuf,
+    pub packages: Vec<Package>,
+    /// `[workspace.metadata]` in `Cargo.toml` of the workspace root.
+    pub workspace_metadata: Option<Value>,
+}
+
+#[derive(Debug)]
+pub struct Package {
+    pub name: String,
+    /// Absolute path to `Cargo.toml` of the package.
+    pub manifest_path: String,
+    pub targets: Vec<Target>,
+    /// `[package.metadata]` in `Cargo.toml` of the package.
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug)]
+pub struct Target {
+    pub name: String,
+    /// Like `lib`, `bin`, `test`, `example`, `bench`, or `proc-macro`.
+    pub kind: Vec<String>,
+    /// Absolute path to the root source file.
+    pub src_path: String,
+}
+
+impl Metadata {
+    /// Run `cargo metadata` for the workspace in the current directory, or the one of
+    /// `--manifest-path` in `forward`, the arguments to `cargo test`.
+    pub fn load(forward: &[String]) -> io::Result<Metadata> {
+        let mut cmd = Command::new("cargo");
+        cmd.args(["metadata", "--format-version", "1", "--no-deps"]);
+        if let Some(path) = cargo_values(forward, "manifest-path").pop() {
+            cmd.args(["--manifest-path", path]);
+        }
+        let output = cmd.output()?;
+        if !output.status.success() {
+            return Err(Error::other(String::from_utf8_lossy(&output.stderr)));
+        }
+        Metadata::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Parse the output of `cargo metadata --format-version 1`.
+    pub fn parse(json: &str) -> io::Result<Metadata> {
+        let json: Value = serde_json::from_str(json).map_err(Error::other)?;
+        let string = |json: &Value, key| {
+            json.get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned()
+        };
+        let table = |json: &Value, key| json.get(key).filter(|v| !v.is_null()).cloned();
+        let array = |json: &Value, key| -> Vec<Value> {
+            json.get(key)
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default()
+        };
+        let packages = array(&json, "packages")
+            .iter()
+            .map(|pkg| Package {
+                name: string(pkg, "name"),
+                manifest_path: string(pkg, "manifest_path"),
+                targets: array(pkg, "targets")
+                    .iter()
+                    .map(|target| Target {
+                        name: string(target, "name"),
+                        kind: array(target, "kind")
+                            .iter()
+                            .filter_map(|k| k.as_str().map(String::from))
+                            .collect(),
+                        src_path: string(target, "src_path"),
+                    })
+                    .collect(),
+                metadata: table(pkg, "metadata"),
+            })
+            .collect();
+        Ok(Metadata {
+            target_directory: string(&json, "target_directory").into(),
+            packages,
+            workspace_metadata: table(&json, "workspace_metadata"),
+        })
+    }
+
+    /// The `cargo test` arguments to select the target of the failed test,
+    /// like `-p integration --test parsing`.
+    pub fn selector(&self, test: &FailedTest) -> Option<Vec<String>> {
+        // binary names use `_` in place of `-`
+        let same_name = |target: &Target, name: &str| target.name.replace('-', "_") == name;
+        let (kind, flag) = match test.ty {
+            TestType::UnitLib | TestType::Doc => ("lib", None),
+            TestType::UnitBin => ("bin", Some("--bin")),
+            TestType::Tests => ("test", Some("--test")),
+            TestType::Examples => ("example", Some("--example")),
+            TestType::Benches => ("bench", Some("--bench")),
+        };
+        let is_kind = |target: &Target| {
+            target.kind.iter().any(|k| match kind {
+                // also rlib, dylib, cdylib, staticlib
+                "lib" => k.ends_with("lib") || k == "proc-macro",
+                _ => k == kind,
+            })
+        };
+        let (pkg, target) = self.packages.iter().find_map(|pkg| {
+            pkg.targets
+                .iter()
+                .find(|target| {
+                    // doc tests are named after the crate instead of the source
+                    let same_src = test.ty == TestType::Doc
+                        || Path::new(&target.src_path).ends_with(&test.src_path);
+                    is_kind(target) && same_src && same_name(target, &test.bin_name)
+                })
+                .map(|target| (pkg, target))
+        })?;
+        let mut args = vec!["-p".to_owned(), pkg.name.clone()];
+        match (test.ty, flag) {
+            (TestType::UnitLib, _) => args.push("--lib".into()),
+            (TestType::Doc, _) => args.push("--doc".into()),
+            (_, Some(flag)) => args.extend([flag.to_owned(), target.name.clone()]),
+            (_, None) => (),
+        }
+        Some(args)
+    }
+}
+
+/// The target dir from `CARGO_TARGET_DIR` or `cargo metadata`, where `forward` is
+/// the arguments to `cargo test`.
+pub fn target_dir(forward: &[String]) -> io::Result<PathBuf> {
+    match std::env::var_os("CARGO_TARGET_DIR") {
+        Some(dir) if !dir.is_empty() => Ok(dir.into()),
+        _ => Metadata::load(forward).map(|meta| meta.target_directory),
+    }
+}
+
+/// Collect the failed tests from parsed test runners.
+pub fn failed_tests(runners: &TestRunners) -> Vec<FailedTest> {
+    tests_with_status(runners, Status::Failed)
+}
+
+/// Collect the tests in the status from parsed test runners.
+pub fn tests_with_status(runners: &TestRunners, status: Status) -> Vec<FailedTest> {
+    runners
+        .pkgs
+        .values()
+        .flat_map(|pkg| &pkg.inner)
+        .flat_map(|data| {
+            data.info
+                .parsed
+                .tests
+                .iter()
+                .filter(move |test| test.status == status)
+                .map(|test| FailedTest {
+                    ty: data.runner.ty,
+                    src_path: data.runner.src.src_path.to_owned(),
+                    bin_name: data.runner.src.bin_name.to_owned(),
+                    name: test.name.to_owned(),
+                })
+        })
+        .collect()
+}
+
+impl FailedTest {
+    /// Whether it's the test from the test runner.
+    pub fn is(&self, runner: &TestRunner, test: &TestCase) -> bool {
+        self.ty == runner.ty
+            && self.src_path == runner.src.src_path
+            && self.bin_name == runner.src.bin_name
+            && self.name == test.name
+    }
+}
+
+/// Record the failed tests in the target dir.
+pub fn save_failed(target_dir: &Path, tests: &[FailedTest]) -> io::Result<()> {
+    let dir = target_dir.join(DATA_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let mut text = String::new();
+    for test in tests {
+        let ty = format!("{:?}", test.ty);
+        text.extend([
+            &ty,
+            "\t",
+            &test.src_path,
+            "\t",
+            &test.bin_name,
+            "\t",
+            &test.name,
+            "\n",
+        ]);
+    }
+    std::fs::write(dir.join(LAST_FAILED), text)
+}
+
+/// Read the failed tests recorded by the last run. No record means no failed test.
+pub fn load_failed(target_dir: &Path) -> io::Result<Vec<FailedTest>> {
+    let text = match std::fs::read_to_string(target_dir.join(DATA_DIR).join(LAST_FAILED)) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    text.lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let invalid = || Error::other(format!("invalid record of a failed test: {line:?}"));
+            let mut fields = line.splitn(4, '\t');
+            let mut next = || fields.next().ok_or_else(invalid);
+            let ty = match next()? {
+                "UnitLib" => TestType::UnitLib,
+                "UnitBin" => TestType::UnitBin,
+                "Doc" => TestType::Doc,
+                "Tests" => TestType::Tests,
+                "Examples" => TestType::Examples,
+                "Benches" => TestType::Benches,
+                _ => return Err(invalid()),
+            };
+            Ok(FailedTest {
+                ty,
+                src_path: next()?.to_owned(),
+                bin_name: next()?.to_owned(),
+                name: next()?.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Build the arguments of `cargo test` for each target to rerun the failed tests.
+///
+/// `forward` is the arguments passed to `cargo test` where the ones after `--`
+/// go to test binaries. Tests whose target can't be found are skipped with a warning.
+pub fn rerun_args(meta: &Metadata, tests: &[FailedTest], forward: &[String]) -> Vec<Vec<String>> {
+    let mut targets = IndexMap::<(Vec<String>, bool), Vec<&str>>::new();
+    for test in tests {
+        if let Some(selector) = meta.selector(test) {
+            let is_doc = test.ty == TestType::Doc;
+            let filter = if is_doc {
+                doc_filter(&test.name)
+            } else {
+                &test.name
+            };
+            targets.entry((selector, is_doc)).or_default().push(filter);
+        } else {
+            eprintln!(
+                "can't find the target of `{}` in {}, skip it",
+                test.name, test.src_path
+            );
+        }
+    }
+    targets
+        .into_iter()
+        .map(|((selector, is_doc), names)| test_args(forward, selector, !is_doc, &names))
+        .collect()
+}
+
+/// Build the arguments of `cargo test` to run the tests in the target of `test` whose names
+/// contain `filter`, or equal it if `exact`. No filter means all the tests in the target.
+///
+/// Doc tests are never matched exactly, because rustdoc splits filters by whitespace.
+pub fn filter_args(
+    meta: &Metadata,
+    test: &FailedTest,
+    filter: Option<&str>,
+    exact: bool,
+    forward: &[String],
+) -> Option<Vec<String>> {
+    let selector = meta.selector(test)?;
+    let is_doc = test.ty == TestType::Doc;
+    let filters: Vec<_> = match filter {
+        Some(filter) if is_doc => vec![doc_filter(filter)],
+        Some(filter) => vec![filter],
+        None => Vec::new(),
+    };
+    Some(test_args(forward, selector, exact && !is_doc, &filters))
+}
+
+/// Put the target selector and test filters into the forwarded arguments.
+fn test_args(
+    forward: &[String],
+    selector: Vec<String>,
+    exact: bool,
+    filters: &[&str],
+) -> Vec<String> {
+    let split = forward
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(forward.len());
+    let (cargo_args, test_args) = forward.split_at(split);
+    let mut args = without_package_selection(cargo_args);
+    args.extend(selector);
+    args.push("--".into());
+    args.extend(test_args.iter().skip(1).cloned());
+    if exact {
+        args.push("--exact".into());
+    }
+    args.extend(filters.iter().map(|f| (*f).to_owned()));
+    args
+}
+
+/// Drop the options selecting packages, like `--workspace` or `-p pkg`, which would
+/// run other packages besides the one of the target selector.
+fn without_package_selection(cargo_args: &[String]) -> Vec<String> {
+    let mut args = Vec::with_capacity(cargo_args.len());
+    let mut iter = cargo_args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--workspace" | "--all" => {}
+            "-p" | "--package" | "--exclude" => _ = iter.next(),
+            arg if arg.starts_with("--package=")
+                || arg.starts_with("--exclude=")
+                || arg.starts_with("-p") => {}
+            _ => args.push(arg.clone()),
+        }
+    }
+    args
+}
+
+/// rustdoc splits test filters by whitespace, so a doc test like
+/// `src/lib.rs - attribute::should_panic (line 80)` can't be matched exactly.
+/// Filter by the item path instead, or the file path for crate-level doc tests.
+fn doc_filter(name: &str) -> &str {
+    let (file, rest) = name.split_once(" - ").unwrap_or((name, ""));
+    match rest.split_once(" (line ") {
+        Some((item, _)) if !item.is_empty() => item,
+        _ => file,
+    }
+}
@@ -0,0 +1,221 @@
+//! Run `cargo test` and render each test runner as soon as it finishes.
+//!
+//! Test runners are executed one by one: stderr tells which runner starts
+//! (`Running unittests src/lib.rs (...)`) and stdout tells its results which end with
+//! a `test result: ...` line. So the n-th runner from stderr pairs with the n-th block
+//! from stdout once both are available.
+
+use crate::{
+    events::events_to_text,
+    fetch::make_runner_tree,
+    parsing::{parse_stderr, parse_stdout, pkg_name, Data},
+    prettify::Options,
+    regex::re,
+    Error, Result,
+};
+use colored::Colorize;
+use serde_json::Value;
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read},
+    process::{Command, Output, Stdio},
+    sync::mpsc,
+    thread,
+};
+
+/// A line printed by the child process, including the line break if any.
+pub enum Line {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+}
+
+/// Run the command with piped stdout & stderr, calling `on_line` for each line as soon
+/// as it's printed, and collect the whole output.
+pub fn spawn(cmd: &mut Command, mut on_line: impl FnMut(&Line)) -> std::io::Result<Output> {
+    fn forward<R: Read + Send + 'static>(
+        pipe: Option<R>,
+        tx: mpsc::Sender<Line>,
+        wrap: fn(Vec<u8>) -> Line,
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let Some(pipe) = pipe else { return };
+            let mut reader = BufReader::new(pipe);
+            loop {
+                let mut line = Vec::new();
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) | Err(_) => return,
+                    Ok(_) => {
+                        if tx.send(wrap(line)).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let (tx, rx) = mpsc::channel();
+    let readers = [
+        forward(child.stdout.take(), tx.clone(), Line::Stdout),
+        forward(child.stderr.take(), tx, Line::Stderr),
+    ];
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    for line in rx {
+        on_line(&line);
+        match line {
+            Line::Stdout(l) => stdout.extend(l),
+            Line::Stderr(l) => stderr.extend(l),
+        }
+    }
+    for reader in readers {
+        _ = reader.join();
+    }
+    Ok(Output {
+        status: child.wait()?,
+        stdout,
+        stderr,
+    })
+}
+
+/// Incrementally split the output into stderr & stdout texts for each test runner.
+#[derive(Debug, Default)]
+pub struct Incremental {
+    /// Lines from stderr that start test runners, but don't have their results yet.
+    runners: VecDeque<String>,
+    /// Finished blocks from stdout, waiting for their test runners.
+    blocks: VecDeque<String>,
+    /// The block being collected from stdout.
+    block: Option<String>,
+}
+
+impl Incremental {
+    /// Feed a line from stderr without the ANSI escapes.
+    pub fn push_stderr(&mut self, line: &str) {
+        if re().ty.is_match(line) {
+            self.runners.push_back(line.to_owned());
+        }
+    }
+
+    /// Feed a line from stdout without the ANSI escapes.
+    pub fn push_stdout(&mut self, line: &str) {
+        if line.starts_with("running ") && re().head.is_match(line) {
+            self.block = Some(String::new());
+        }
+        if let Some(block) = &mut self.block {
+            block.push_str(line);
+            block.push('\n');
+        }
+        if re().stats.is_match(line) {
+            if let Some(block) = self.block.take() {
+                self.blocks.push_back(block);
+            }
+        }
+    }
+
+    /// Feed texts from stdout consisting of multiple lines.
+    pub fn push_stdout_text(&mut self, text: &str) {
+        for line in text.lines() {
+            self.push_stdout(line);
+        }
+    }
+
+    /// Pop a finished test runner as `(stderr, stdout)` which can be parsed
+    /// by [`parse_stderr`] and [`parse_stdout`].
+    pub fn pop(&mut self) -> Option<(String, String)> {
+        if self.runners.is_empty() || self.blocks.is_empty() {
+            return None;
+        }
+        self.runners.pop_front().zip(self.blocks.pop_front())
+    }
+}
+
+/// Print each test runner in the tree form once it finishes.
+#[derive(Debug, Default)]
+pub struct LivePrinter {
+    parser: Incremental,
+    /// The pkg of the last test runner.
+    pkg: Option<String>,
+    /// The pkg whose name has been printed.
+    printed_pkg: Option<String>,
+    /// Collect JSON events of the current test runner if `--libtest-json` is used.
+    events: Option<String>,
+    opts: Options,
+}
+
+impl LivePrinter {
+    pub fn new(libtest_json: bool, opts: Options) -> Self {
+        LivePrinter {
+            events: libtest_json.then(String::new),
+            opts,
+            ..Default::default()
+        }
+    }
+
+    pub fn feed(&mut self, line: &Line) {
+        match line {
+            Line::Stderr(l) => self.parser.push_stderr(&strip(l)),
+            Line::Stdout(l) => {
+                let line = strip(l);
+                if let Some(events) = &mut self.events {
+                    events.push_str(&line);
+                    events.push('\n');
+                    if is_suite_end(&line) {
+                        self.parser.push_stdout_text(&events_to_text(events));
+                        events.clear();
+                    }
+                } else {
+                    self.parser.push_stdout(&line);
+                }
+            }
+        }
+        while let Some((stderr, stdout)) = self.parser.pop() {
+            if let Err(err) = self.print(&stderr, &stdout) {
+                eprintln!("{}: {err}", "Error from cargo-pretty-test".red().bold());
+            }
+        }
+    }
+
+    fn print(&mut self, stderr: &str, stdout: &str) -> Result<()> {
+        let runner = parse_stderr(stderr)?.pop().ok_or(Error::NoTestRunner)?;
+        let info = parse_stdout(stdout)?.pop().ok_or(Error::NoTestRunning)?;
+        if let Some(pkg) = pkg_name(&runner) {
+            self.pkg = Some(pkg.to_owned());
+        }
+        // don't show test types that have no tests
+        if info.stats.total == 0 {
+            return Ok(());
+        }
+        if self.printed_pkg != self.pkg {
+            self.printed_pkg.clone_from(&self.pkg);
+            let pkg = self.pkg.as_deref().unwrap_or("tests");
+            println!("{}", pkg.blue().bold());
+        }
+        if let Some(tree) = make_runner_tree(&Data { runner, info }, &self.opts) {
+            println!("{tree}");
+        }
+        Ok(())
+    }
+}
+
+/// Strip ANSI escapes from the line without the line break.
+fn strip(line: &[u8]) -> String {
+    let line = strip_ansi_escapes::strip(line);
+    String::from_utf8_lossy(&line).trim_end().to_owned()
+}
+
+/// Whether the JSON event finishes a test suite, i.e. a test runner.
+fn is_suite_end(line: &str) -> bool {
+    line.trim_start().starts_with('{')
+        && serde_json::from_str::<Value>(line).is_ok_and(|event| {
+            event.get("type").and_then(Value::as_str) == Some("suite")
+                && matches!(
+                    event.get("event").and_then(Value::as_str),
+                    Some("ok" | "failed")
+                )
+        })
+}
@@ -0,0 +1,147 @@
+//! Icons of test statuses in the tree, the summaries and the help.
+//!
+//! Built-in themes are `emoji` (default), `ascii` for consoles without emoji, like `[PASS]`,
+//! and `nerdfont` for terminals with a [Nerd Font](https://www.nerdfonts.com). Icons can also
+//! be picked one by one after a theme, like `--icons ascii,flaky=[RETRY]`.
+
+use crate::prettify::Status;
+use std::sync::OnceLock;
+
+pub const THEMES: &[&str] = &["emoji", "ascii", "nerdfont"];
+
+/// Icons of each status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub passed: String,
+    pub failed: String,
+    pub flaky: String,
+    pub ignored: String,
+    pub filtered_out: String,
+    /// Item indents of tests in the tree, like `─ ✅ `, by status.
+    indents: [String; 4],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::new(["✅", "❌", "🔁", "🔕", "✂️"])
+    }
+}
+
+impl Theme {
+    /// Icons of passed, failed, flaky, ignored and filtered out tests.
+    pub fn new(icons: [&str; 5]) -> Theme {
+        let [passed, failed, flaky, ignored, filtered_out] = icons.map(String::from);
+        let mut theme = Theme {
+            passed,
+            failed,
+            flaky,
+            ignored,
+            filtered_out,
+            indents: Default::default(),
+        };
+        theme.update_indents();
+        theme
+    }
+
+    /// A built-in theme by its name.
+    pub fn named(name: &str) -> Option<Theme> {
+        match name {
+            "emoji" => Some(Theme::default()),
+            "ascii" => Some(Theme::new([
+                "[PASS]",
+                "[FAIL]",
+                "[FLAKY]",
+                "[SKIP]",
+                "[FILTERED]",
+            ])),
+            "nerdfont" => Some(Theme::new([
+                "\u{f00c}", // nf-fa-check
+                "\u{f00d}", // nf-fa-times
+                "\u{f021}", // nf-fa-refresh
+                "\u{f1f6}", // nf-fa-bell_slash
+                "\u{f0c4}", // nf-fa-scissors
+            ])),
+            _ => None,
+        }
+    }
+
+    /// Parse comma-separated theme names and icons of statuses, like `ascii,flaky=[RETRY]`,
+    /// where latter ones win and icons not given are emoji.
+    pub fn parse(spec: &str) -> Result<Theme, String> {
+        let mut theme = Theme::default();
+        for item in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let Some((status, icon)) = item.split_once('=') else {
+                theme = Theme::named(item).ok_or_else(|| {
+                    format!(
+                        "unknown theme `{item}`, expected one of {} or `<status>=<icon>`",
+                        THEMES.join(", ")
+                    )
+                })?;
+                continue;
+            };
+            let icon = icon.trim().to_owned();
+            match status.trim() {
+                "ok" | "passed" => theme.passed = icon,
+                "failed" => theme.failed = icon,
+                "flaky" => theme.flaky = icon,
+                "ignored" => theme.ignored = icon,
+                "filtered" => theme.filtered_out = icon,
+                status => {
+                    return Err(format!(
+                        "unknown status `{status}`, expected passed, failed, flaky, ignored \
+                         or filtered"
+                    ))
+                }
+            }
+        }
+        theme.update_indents();
+        Ok(theme)
+    }
+
+    fn update_indents(&mut self) {
+        for status in [Status::Ok, Status::Ignored, Status::Failed, Status::Flaky] {
+            self.indents[status as usize] = format!("─ {} ", self.icon(status));
+        }
+    }
+
+    pub fn icon(&self, status: Status) -> &str {
+        match status {
+            Status::Ok => &self.passed,
+            Status::Ignored => &self.ignored,
+            Status::Failed => &self.failed,
+            Status::Flaky => &self.flaky,
+        }
+    }
+
+    /// The item indent of a test in the tree, like `─ ✅ `.
+    pub fn indent(&self, status: Status) -> &str {
+        &self.indents[status as usize]
+    }
+
+    /// Explanation of the icons, printed in the help.
+    pub fn notation(&self) -> String {
+        format!(
+            "
+Icon Notation:
+─ {} pass (including the case that should panic and did panic)
+─ {} fail (including the case that should panic but didn't panic)
+─ {} flaky (failed but passed on retry with --retries)
+─ {} ignored (with reason omitted)
+─ {} filtered out (won't show in the test tree, but will be computed in the summary)
+",
+            self.passed, self.failed, self.flaky, self.ignored, self.filtered_out
+        )
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Use the theme for the rest of the process. Only the first call takes effect.
+pub fn set_theme(theme: Theme) {
+    _ = THEME.set(theme);
+}
+
+/// The theme in use, which is emoji unless [`set_theme`] is called.
+pub fn theme() -> &'static Theme {
+    THEME.get_or_init(Theme::default)
+}
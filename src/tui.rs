@@ -0,0 +1,805 @@
+//! Browse the test tree in an interactive terminal UI with `--tui`.
+//!
+//! The tree has the same package → runner → module → test hierarchy as the static one,
+//! where nodes can be collapsed, filtered by status or substring, and rerun. The failure
+//! of the selected test is shown in the side pane.
+//!
+//! Keys:
+//! - `↑`/`↓` or `k`/`j`: move the selection; `PgUp`/`PgDn`/`Home`/`End`: move further
+//! - `←`/`→` or `h`/`l`: collapse/expand the node; `Enter` or `Space`: toggle it
+//! - `s`: cycle the status filter through all, failed, flaky, ok and ignored
+//! - `/`: type a substring to filter tests by, `Enter` to confirm, `Esc` to clear
+//! - `r`: rerun the tests under the selected node
+//! - `q` or `Esc`: quit
+
+use crate::{
+    diagnostic::parse_build_failure,
+    failure::Failure,
+    fetch::cargo_test_output,
+    parsing::{parse_cargo_test, TestCase, TestRunner, TestRunners, TestType},
+    prettify::{sorted, Module, Node as TreeNode, Options, Sort, Status},
+    rerun::{filter_args, FailedTest, Metadata},
+};
+pub use crossterm::event::KeyCode;
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{self, Write},
+    time::Duration,
+};
+
+/// Status filters cycled through with `s`.
+const STATUS_FILTERS: [Option<Status>; 5] = [
+    None,
+    Some(Status::Failed),
+    Some(Status::Flaky),
+    Some(Status::Ok),
+    Some(Status::Ignored),
+];
+
+const HELP: &str = "↑↓ move  ←→ fold  s status  / search  r rerun  q quit";
+
+/// What to do after a key is pressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Continue,
+    /// Rerun the tests under the selected node.
+    Rerun,
+    Quit,
+}
+
+/// The state of the interactive tree.
+#[derive(Debug)]
+pub struct App {
+    nodes: Vec<Node>,
+    roots: Vec<usize>,
+    /// Test nodes by the test they stand for, to update them after a rerun.
+    tests: HashMap<TestKey, usize>,
+    /// Position of the selected node in the visible nodes.
+    selected: usize,
+    /// Position of the first visible node on the screen.
+    scroll: usize,
+    status_filter: Option<Status>,
+    query: String,
+    /// Typing the query after `/`.
+    typing: bool,
+    /// Shown at the bottom, like the result of a rerun.
+    message: String,
+    /// Loaded on the first rerun.
+    meta: Option<Metadata>,
+    forward: Vec<String>,
+    libtest_json: bool,
+}
+
+#[derive(Debug)]
+struct Node {
+    label: String,
+    kind: Kind,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    expanded: bool,
+    /// The worst status of the tests under it.
+    status: Status,
+    /// Whether it or any test under it passes the filters.
+    matched: bool,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Pkg,
+    /// All the tests from a test binary.
+    Runner(RunnerKey),
+    /// Tests whose names start with `path::`.
+    Module {
+        runner: RunnerKey,
+        path: String,
+    },
+    Test(Box<TestNode>),
+}
+
+/// The test runner of nodes, to rerun the tests under them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RunnerKey {
+    ty: TestType,
+    src_path: String,
+    bin_name: String,
+}
+
+impl RunnerKey {
+    fn new(runner: &TestRunner) -> RunnerKey {
+        RunnerKey {
+            ty: runner.ty,
+            src_path: runner.src.src_path.to_owned(),
+            bin_name: runner.src.bin_name.to_owned(),
+        }
+    }
+
+    /// The target to pass to [`filter_args`], which only reads the runner of the test.
+    fn target(&self) -> FailedTest {
+        FailedTest {
+            ty: self.ty,
+            src_path: self.src_path.clone(),
+            bin_name: self.bin_name.clone(),
+            name: String::new(),
+        }
+    }
+}
+
+/// A test by its runner and full name, which stays the same across reruns.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TestKey {
+    runner: RunnerKey,
+    name: String,
+}
+
+#[derive(Debug)]
+struct TestNode {
+    test: TestKey,
+    status: Status,
+    duration: Option<Duration>,
+    ignore_reason: Option<String>,
+    /// Lines shown in the side pane for a failed test.
+    failure: Vec<String>,
+    /// Lines printed by a passed test with `--show-output` or `--nocapture`.
+    output: Vec<String>,
+}
+
+/// Run the interactive UI until quitting.
+pub fn browse(runners: &TestRunners, forward: Vec<String>, libtest_json: bool) -> io::Result<()> {
+    let mut app = App::new(runners, forward, libtest_json);
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    let result = queue!(out, EnterAlternateScreen, Hide).and_then(|()| app.event_loop(&mut out));
+    // restore the terminal even if the UI fails
+    _ = queue!(out, Show, LeaveAlternateScreen);
+    _ = out.flush();
+    _ = terminal::disable_raw_mode();
+    result
+}
+
+impl App {
+    pub fn new(runners: &TestRunners, forward: Vec<String>, libtest_json: bool) -> App {
+        let mut app = App {
+            nodes: Vec::new(),
+            roots: Vec::new(),
+            tests: HashMap::new(),
+            selected: 0,
+            scroll: 0,
+            status_filter: None,
+            query: String::new(),
+            typing: false,
+            message: String::new(),
+            meta: None,
+            forward,
+            libtest_json,
+        };
+        for (pkg, pkg_test) in &runners.pkgs {
+            let pkg = app.push(None, pkg.unwrap_or("tests").to_owned(), Kind::Pkg);
+            for data in &pkg_test.inner {
+                let runner = RunnerKey::new(&data.runner);
+                let id = app.push(
+                    Some(pkg),
+                    runner.src_path.clone(),
+                    Kind::Runner(runner.clone()),
+                );
+                let tests = &data.info.parsed.tests;
+                let module = Module::new(tests, &Options::default());
+                let tests = tests.iter().map(|test| (test.name, test)).collect();
+                app.push_module(id, &runner, module, "", &tests);
+            }
+        }
+        app.refresh();
+        // show where the failures are
+        for node in &mut app.nodes {
+            node.expanded = matches!(node.kind, Kind::Pkg) || node.status == Status::Failed;
+        }
+        app
+    }
+
+    fn push(&mut self, parent: Option<usize>, label: String, kind: Kind) -> usize {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            label,
+            kind,
+            parent,
+            children: Vec::new(),
+            expanded: false,
+            status: Status::Ignored,
+            matched: true,
+        });
+        match parent {
+            Some(parent) => self.nodes[parent].children.push(id),
+            None => self.roots.push(id),
+        }
+        id
+    }
+
+    /// Add the modules and tests under the node in the order of the static tree,
+    /// where `path` is the path of the module like `submod::panic`.
+    fn push_module(
+        &mut self,
+        parent: usize,
+        runner: &RunnerKey,
+        module: Module,
+        path: &str,
+        tests: &HashMap<&str, &TestCase>,
+    ) {
+        for (name, node) in sorted(module.children, Sort::Name) {
+            match node {
+                TreeNode::Path(module) => {
+                    let path = if path.is_empty() {
+                        name.to_owned()
+                    } else {
+                        format!("{path}::{name}")
+                    };
+                    let kind = Kind::Module {
+                        runner: runner.clone(),
+                        path: path.clone(),
+                    };
+                    let id = self.push(Some(parent), name.to_owned(), kind);
+                    self.push_module(id, runner, module, &path, tests);
+                }
+                TreeNode::Test(leaf) => {
+                    let Some(test) = tests.get(leaf.name) else {
+                        continue;
+                    };
+                    let key = TestKey {
+                        runner: runner.clone(),
+                        name: test.name.to_owned(),
+                    };
+                    let node = TestNode {
+                        test: key.clone(),
+                        status: test.status,
+                        duration: test.duration,
+                        ignore_reason: test.ignore_reason.map(String::from),
+                        failure: test.failure.as_ref().map(failure_lines).unwrap_or_default(),
+                        output: output_lines(test.output),
+                    };
+                    let id = self.push(Some(parent), name.to_owned(), Kind::Test(Box::new(node)));
+                    self.tests.insert(key, id);
+                }
+            }
+        }
+    }
+
+    /// Recompute statuses and filter results of all the nodes.
+    fn refresh(&mut self) {
+        let query = self.query.to_lowercase();
+        for i in 0..self.roots.len() {
+            self.refresh_node(self.roots[i], &query);
+        }
+        let len = self.visible().len();
+        self.selected = self.selected.min(len.saturating_sub(1));
+    }
+
+    fn refresh_node(&mut self, id: usize, query: &str) -> (Status, bool) {
+        let (status, matched) = if let Kind::Test(test) = &self.nodes[id].kind {
+            let name = format!("{} {}", test.test.runner.src_path, test.test.name).to_lowercase();
+            let matched = self.status_filter.is_none_or(|s| s == test.status)
+                && (query.is_empty() || name.contains(query));
+            (test.status, matched)
+        } else {
+            let mut status = Status::Ignored;
+            let mut matched = false;
+            for child in self.nodes[id].children.clone() {
+                let (s, m) = self.refresh_node(child, query);
+                status = status.max(s);
+                matched |= m;
+            }
+            (status, matched)
+        };
+        let node = &mut self.nodes[id];
+        node.status = status;
+        node.matched = matched;
+        (status, matched)
+    }
+
+    /// Apply changed filters, and expand the nodes with matched tests.
+    fn apply_filters(&mut self) {
+        self.refresh();
+        if self.status_filter.is_some() || !self.query.is_empty() {
+            for node in &mut self.nodes {
+                node.expanded |= node.matched;
+            }
+        }
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    /// Visible nodes as `(id, depth)` from top to bottom.
+    fn visible(&self) -> Vec<(usize, usize)> {
+        let mut visible = Vec::new();
+        let mut stack: Vec<_> = self.roots.iter().rev().map(|&id| (id, 0)).collect();
+        while let Some((id, depth)) = stack.pop() {
+            let node = &self.nodes[id];
+            if !node.matched {
+                continue;
+            }
+            visible.push((id, depth));
+            if node.expanded {
+                stack.extend(node.children.iter().rev().map(|&c| (c, depth + 1)));
+            }
+        }
+        visible
+    }
+
+    fn selected_node(&self) -> Option<usize> {
+        self.visible().get(self.selected).map(|&(id, _)| id)
+    }
+
+    /// Handle a key, where `page` is the number of rows of the tree.
+    pub fn press(&mut self, key: KeyCode, page: usize) -> Action {
+        if self.typing {
+            match key {
+                KeyCode::Char(c) => self.query.push(c),
+                KeyCode::Backspace => _ = self.query.pop(),
+                KeyCode::Enter => self.typing = false,
+                KeyCode::Esc => {
+                    self.typing = false;
+                    self.query.clear();
+                }
+                _ => return Action::Continue,
+            }
+            self.apply_filters();
+            return Action::Continue;
+        }
+        let last = self.visible().len().saturating_sub(1);
+        match key {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Esc if self.query.is_empty() => return Action::Quit,
+            KeyCode::Esc => {
+                self.query.clear();
+                self.apply_filters();
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(page),
+            KeyCode::PageDown => self.selected = (self.selected + page).min(last),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = last,
+            KeyCode::Left | KeyCode::Char('h') => self.collapse(),
+            KeyCode::Right | KeyCode::Char('l') => self.set_expanded(true),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let expanded = self
+                    .selected_node()
+                    .is_some_and(|id| self.nodes[id].expanded);
+                self.set_expanded(!expanded);
+            }
+            KeyCode::Char('s') => {
+                let pos = STATUS_FILTERS
+                    .iter()
+                    .position(|&s| s == self.status_filter)
+                    .unwrap_or(0);
+                self.status_filter = STATUS_FILTERS[(pos + 1) % STATUS_FILTERS.len()];
+                self.apply_filters();
+            }
+            KeyCode::Char('/') => self.typing = true,
+            KeyCode::Char('r') => return Action::Rerun,
+            _ => (),
+        }
+        Action::Continue
+    }
+
+    fn set_expanded(&mut self, expanded: bool) {
+        if let Some(id) = self.selected_node() {
+            self.nodes[id].expanded = expanded;
+        }
+    }
+
+    /// Collapse the selected node, or select its parent if it's collapsed or a test.
+    fn collapse(&mut self) {
+        let Some(id) = self.selected_node() else {
+            return;
+        };
+        let node = &self.nodes[id];
+        if node.expanded && !node.children.is_empty() {
+            self.nodes[id].expanded = false;
+        } else if let Some(parent) = node.parent {
+            if let Some(pos) = self.visible().iter().position(|&(id, _)| id == parent) {
+                self.selected = pos;
+            }
+        }
+    }
+
+    /// Targets and test filters to rerun the tests under the node.
+    fn filters(&self, id: usize) -> Vec<(&RunnerKey, Option<&str>, bool)> {
+        match &self.nodes[id].kind {
+            Kind::Pkg => self.nodes[id]
+                .children
+                .iter()
+                .flat_map(|&child| self.filters(child))
+                .collect(),
+            Kind::Runner(runner) => vec![(runner, None, false)],
+            // rustdoc can't filter by a module path with spaces
+            Kind::Module { runner, .. } if runner.ty == TestType::Doc => {
+                vec![(runner, None, false)]
+            }
+            Kind::Module { runner, path } => vec![(runner, Some(path.as_str()), false)],
+            Kind::Test(test) => vec![(&test.test.runner, Some(test.test.name.as_str()), true)],
+        }
+    }
+
+    /// Rerun the tests under the selected node, and update them with the results.
+    pub fn rerun(&mut self) {
+        let Some(id) = self.selected_node() else {
+            return;
+        };
+        if self.meta.is_none() {
+            match Metadata::load(&self.forward) {
+                Ok(meta) => self.meta = Some(meta),
+                Err(err) => {
+                    self.message = format!("Failed to run `cargo metadata`: {err}");
+                    return;
+                }
+            }
+        }
+        let Some(meta) = &self.meta else { return };
+        let commands: Vec<_> = self
+            .filters(id)
+            .into_iter()
+            .filter_map(|(runner, filter, exact)| {
+                filter_args(meta, &runner.target(), filter, exact, &self.forward)
+            })
+            .collect();
+        if commands.is_empty() {
+            self.message = "Can't find the target of the tests to rerun".into();
+            return;
+        }
+        let mut updated = [0; 2];
+        let mut errors = Vec::new();
+        for args in commands {
+            let (stderr, stdout) = match cargo_test_output(&args, self.libtest_json) {
+                Ok(output) => output,
+                Err(err) => {
+                    errors.push(err.to_string());
+                    continue;
+                }
+            };
+            match parse_cargo_test(&stderr, &stdout) {
+                Ok(runners) => {
+                    for (passed, failed) in self.update(&runners) {
+                        updated[0] += passed;
+                        updated[1] += failed;
+                    }
+                }
+                Err(err) => errors.push(match parse_build_failure(&stderr) {
+                    Some(_) => "Build failed".to_owned(),
+                    None => err.to_string(),
+                }),
+            }
+        }
+        self.refresh();
+        let [passed, failed] = updated;
+        self.message = match errors.first() {
+            Some(err) => format!("Failed to rerun: {err}"),
+            None => format!(
+                "Reran {} tests: {passed} passed; {failed} failed",
+                passed + failed
+            ),
+        };
+    }
+
+    /// Update test nodes with rerun results, and count passed and failed ones.
+    fn update(&mut self, runners: &TestRunners) -> Vec<(u32, u32)> {
+        let mut counts = Vec::new();
+        for data in runners.pkgs.values().flat_map(|pkg| &pkg.inner) {
+            let (mut passed, mut failed) = (0, 0);
+            let runner = RunnerKey::new(&data.runner);
+            for test in &data.info.parsed.tests {
+                let key = TestKey {
+                    runner: runner.clone(),
+                    name: test.name.to_owned(),
+                };
+                let Some(&id) = self.tests.get(&key) else {
+                    continue;
+                };
+                if let Kind::Test(node) = &mut self.nodes[id].kind {
+                    node.status = test.status;
+                    node.duration = test.duration;
+                    node.failure = test.failure.as_ref().map(failure_lines).unwrap_or_default();
+                    node.output = output_lines(test.output);
+                    match test.status {
+                        Status::Ok => passed += 1,
+                        Status::Failed => failed += 1,
+                        Status::Ignored | Status::Flaky => (),
+                    }
+                }
+            }
+            counts.push((passed, failed));
+        }
+        counts
+    }
+
+    /// Lines of the tree pane without styles, like `▾ ❌ panic`.
+    pub fn tree_lines(&self) -> Vec<String> {
+        self.visible()
+            .into_iter()
+            .map(|(id, depth)| self.tree_line(id, depth))
+            .collect()
+    }
+
+    fn tree_line(&self, id: usize, depth: usize) -> String {
+        let node = &self.nodes[id];
+        let fold = match (&node.kind, node.expanded) {
+            (Kind::Test(_), _) => " ",
+            (_, true) => "▾",
+            (_, false) => "▸",
+        };
+        let mut line = format!(
+            "{:indent$}{fold} {} {}",
+            "",
+            node.status.symbol(),
+            node.label,
+            indent = depth * 2
+        );
+        if let Kind::Test(test) = &node.kind {
+            if let Some(d) = test.duration {
+                _ = write!(line, " <{:.3}s>", d.as_secs_f64());
+            }
+        }
+        line
+    }
+
+    /// Lines of the side pane for the selected node.
+    pub fn detail_lines(&self) -> Vec<String> {
+        let Some(id) = self.selected_node() else {
+            return vec!["No test matches the filters".into()];
+        };
+        let node = &self.nodes[id];
+        if let Kind::Test(test) = &node.kind {
+            let mut lines = vec![test.test.name.clone(), String::new()];
+            lines.push(format!("status: {}", test.status.name()));
+            if let Some(d) = test.duration {
+                lines.push(format!("duration: {:.3}s", d.as_secs_f64()));
+            }
+            if let Some(reason) = &test.ignore_reason {
+                lines.push(format!("ignored: {reason}"));
+            }
+            if !test.failure.is_empty() {
+                lines.push(String::new());
+                lines.extend(test.failure.iter().cloned());
+            }
+            if !test.output.is_empty() {
+                lines.extend([String::new(), "output:".to_owned()]);
+                lines.extend(test.output.iter().cloned());
+            }
+            return lines;
+        }
+        let mut counts = [0; 4];
+        self.count(id, &mut counts);
+        let [ok, failed, flaky, ignored] = counts;
+        vec![
+            node.label.clone(),
+            String::new(),
+            format!("{ok} passed; {failed} failed; {flaky} flaky; {ignored} ignored"),
+            String::new(),
+            "Press r to rerun the tests under it".into(),
+        ]
+    }
+
+    /// Count tests under the node by ok, failed, flaky and ignored.
+    fn count(&self, id: usize, counts: &mut [u32; 4]) {
+        match &self.nodes[id].kind {
+            Kind::Test(test) => {
+                let i = match test.status {
+                    Status::Ok => 0,
+                    Status::Failed => 1,
+                    Status::Flaky => 2,
+                    Status::Ignored => 3,
+                };
+                counts[i] += 1;
+            }
+            _ => {
+                for &child in &self.nodes[id].children {
+                    self.count(child, counts);
+                }
+            }
+        }
+    }
+
+    fn event_loop(&mut self, out: &mut impl Write) -> io::Result<()> {
+        loop {
+            let (width, height) = terminal::size()?;
+            self.draw(out, width, height)?;
+            // resizing also redraws
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                return Ok(());
+            }
+            match self.press(key.code, usize::from(height.saturating_sub(2))) {
+                Action::Continue => (),
+                Action::Quit => return Ok(()),
+                Action::Rerun => {
+                    self.message = "Running cargo test...".into();
+                    self.draw(out, width, height)?;
+                    self.rerun();
+                }
+            }
+        }
+    }
+
+    fn draw(&mut self, out: &mut impl Write, width: u16, height: u16) -> io::Result<()> {
+        let width = usize::from(width);
+        let rows = usize::from(height.saturating_sub(2));
+        let left = (width * 3 / 5).max(20).min(width);
+        let right = width.saturating_sub(left + 1);
+        let visible = self.visible();
+        // keep the selection on the screen
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if rows != 0 && self.selected >= self.scroll + rows {
+            self.scroll = self.selected + 1 - rows;
+        }
+
+        let mut counts = [0; 4];
+        for &root in &self.roots {
+            self.count(root, &mut counts);
+        }
+        let [ok, failed, flaky, ignored] = counts;
+        let [ok_icon, failed_icon, flaky_icon, ignored_icon] =
+            [Status::Ok, Status::Failed, Status::Flaky, Status::Ignored].map(Status::symbol);
+        let mut header = format!(
+            " cargo-pretty-test  {ok_icon} {ok}  {failed_icon} {failed}  \
+             {flaky_icon} {flaky}  {ignored_icon} {ignored}"
+        );
+        if let Some(status) = self.status_filter {
+            _ = write!(header, "  [status: {}]", status.name());
+        }
+        if !self.query.is_empty() {
+            _ = write!(header, "  [search: {}]", self.query);
+        }
+        queue!(
+            out,
+            MoveTo(0, 0),
+            Clear(ClearType::All),
+            SetAttribute(Attribute::Bold),
+            Print(fit(&header, width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+
+        let detail: Vec<_> = self
+            .detail_lines()
+            .iter()
+            .flat_map(|line| wrap(line, right))
+            .collect();
+        for row in 0..rows {
+            let y = u16::try_from(row + 1).unwrap_or(u16::MAX);
+            queue!(out, MoveTo(0, y))?;
+            if let Some(&(id, depth)) = visible.get(self.scroll + row) {
+                if self.scroll + row == self.selected {
+                    queue!(out, SetAttribute(Attribute::Reverse))?;
+                }
+                queue!(
+                    out,
+                    SetForegroundColor(color(self.nodes[id].status)),
+                    Print(fit(&self.tree_line(id, depth), left)),
+                    ResetColor,
+                    SetAttribute(Attribute::Reset)
+                )?;
+            } else {
+                queue!(out, Print(fit("", left)))?;
+            }
+            if right != 0 {
+                let line = detail.get(row).map_or("", String::as_str);
+                queue!(
+                    out,
+                    SetForegroundColor(Color::DarkGrey),
+                    Print("│"),
+                    ResetColor,
+                    Print(fit(line, right))
+                )?;
+            }
+        }
+
+        let footer = if self.typing {
+            format!("/{}█", self.query)
+        } else if self.message.is_empty() {
+            HELP.to_owned()
+        } else {
+            format!("{}  |  {HELP}", self.message)
+        };
+        let y = u16::try_from(rows + 1).unwrap_or(u16::MAX);
+        queue!(
+            out,
+            MoveTo(0, y),
+            SetForegroundColor(Color::DarkGrey),
+            Print(fit(&footer, width)),
+            ResetColor
+        )?;
+        out.flush()
+    }
+}
+
+/// Lines of the panic message, location, assert values and the whole output.
+fn failure_lines(failure: &Failure) -> Vec<String> {
+    let mut lines: Vec<_> = failure.message.lines().map(String::from).collect();
+    if let Some(location) = failure.location {
+        lines.push(format!("at {location}"));
+    }
+    if let (Some(left), Some(right)) = (failure.left, failure.right) {
+        lines.extend([
+            String::new(),
+            format!(" left: {left}"),
+            format!("right: {right}"),
+        ]);
+    }
+    lines.extend([String::new(), "output:".to_owned()]);
+    lines.extend(failure.stdout.lines().map(|line| format!("  {line}")));
+    lines
+}
+
+fn output_lines(output: Option<&str>) -> Vec<String> {
+    output
+        .into_iter()
+        .flat_map(str::lines)
+        .map(|line| format!("  {line}"))
+        .collect()
+}
+
+fn color(status: Status) -> Color {
+    match status {
+        Status::Ok => Color::Green,
+        Status::Ignored => Color::DarkGrey,
+        Status::Failed => Color::Red,
+        Status::Flaky => Color::Yellow,
+    }
+}
+
+/// Columns taken by the character in a terminal, where emojis take two.
+fn char_width(c: char) -> usize {
+    match c {
+        '\u{FE0F}' => 0,
+        '\u{2705}' | '\u{274C}' | '\u{1F300}'.. => 2,
+        _ => 1,
+    }
+}
+
+/// Truncate or pad the text to the width.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = char_width(c);
+        if used + w > width {
+            break;
+        }
+        used += w;
+        fitted.push(c);
+    }
+    fitted.push_str(&" ".repeat(width - used));
+    fitted
+}
+
+/// Split the text into lines no wider than the width.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    if width == 0 {
+        return Vec::new();
+    }
+    let mut lines = vec![String::new()];
+    let mut used = 0;
+    for c in text.chars() {
+        let w = char_width(c);
+        if used + w > width {
+            lines.push(String::new());
+            used = 0;
+        }
+        used += w;
+        if let Some(line) = lines.last_mut() {
+            line.push(c);
+        }
+    }
+    lines
+}
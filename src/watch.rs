@@ -0,0 +1,78 @@
+//! Poll source directories of the workspace for changes in `--watch` mode.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    thread::sleep,
+    time::{Duration, SystemTime},
+};
+
+/// How often the directories are scanned.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Wait this long after a change for other files being saved at the same time.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Directories under each package that trigger a rerun.
+pub const WATCHED_DIRS: [&str; 4] = ["src", "tests", "examples", "benches"];
+
+/// Detect changes by comparing modification times of all the files under the directories.
+#[derive(Debug)]
+pub struct Watcher {
+    roots: Vec<PathBuf>,
+    files: HashMap<PathBuf, SystemTime>,
+}
+
+impl Watcher {
+    /// Watch the directories, which needn't exist yet.
+    pub fn new(roots: Vec<PathBuf>) -> Watcher {
+        let files = scan(&roots);
+        Watcher { roots, files }
+    }
+
+    /// Whether any file is added, removed or modified since the last check.
+    pub fn changed(&mut self) -> bool {
+        let files = scan(&self.roots);
+        let changed = files != self.files;
+        self.files = files;
+        changed
+    }
+
+    /// Take the files as they are now as the baseline, ignoring the changes so far,
+    /// like the files written by tests during a run.
+    pub fn reset(&mut self) {
+        self.files = scan(&self.roots);
+    }
+
+    /// Block until something changes.
+    pub fn wait(&mut self) {
+        while !self.changed() {
+            sleep(POLL_INTERVAL);
+        }
+        sleep(DEBOUNCE);
+        self.changed();
+    }
+}
+
+fn scan(roots: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
+    let mut files = HashMap::new();
+    for root in roots {
+        scan_dir(root, &mut files);
+    }
+    files
+}
+
+/// Unreadable entries are skipped, since they may be removed during the scan.
+fn scan_dir(dir: &Path, files: &mut HashMap<PathBuf, SystemTime>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else { continue };
+        if meta.is_dir() {
+            scan_dir(&entry.path(), files);
+        } else if let Ok(modified) = meta.modified() {
+            files.insert(entry.path(), modified);
+        }
+    }
+}
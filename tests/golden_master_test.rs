@@ -1,4 +1,7 @@
-use cargo_pretty_test::prettify::make_pretty;
+use cargo_pretty_test::{
+    parsing::TestCase,
+    prettify::{make_pretty, Options},
+};
 use pretty_assertions::assert_eq;
 
 #[test]
@@ -44,10 +47,13 @@ test
                                 └─ ✅ it_should_not_allow_a_non_admin_to_ban_a_user
 ";
 
+    let tests: Vec<_> = INPUT
+        .trim()
+        .lines()
+        .filter_map(|line| TestCase::parse(line.trim()))
+        .collect();
     assert_eq!(
-        make_pretty("test", INPUT.trim().lines().map(str::trim))
-            .unwrap()
-            .to_string(),
+        make_pretty("test", &tests, &Options::default()).to_string(),
         OUTPUT
     );
 }
@@ -1,7 +1,7 @@
 use cargo_pretty_test::{
     lazy_static,
     parsing::{parse_stdout, ParsedCargoTestOutput, TestInfo},
-    prettify::make_pretty,
+    prettify::{make_pretty, Options},
 };
 use insta::{assert_debug_snapshot as snap, assert_display_snapshot as shot};
 use regex_lite::Regex;
@@ -18,6 +18,7 @@ lazy_static! {
     parsed_cargo_test, Cache, {
         let output = Command::new("cargo")
             .args(["test", "-p", "integration"])
+            .env_remove("RUST_BACKTRACE")
             .output()
             .unwrap();
         let text = String::from_utf8_lossy(&output.stdout);
@@ -28,7 +29,10 @@ lazy_static! {
         let strip_backtrace = Regex::new("note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n")
             .unwrap()
             .replace_all(&modified_time, "");
-        let raw_output = strip_backtrace.into_owned().leak();
+        let strip_thread_id = Regex::new(r"(?<thread>thread '\S+') \(\d+\)")
+            .unwrap()
+            .replace_all(&strip_backtrace, "$thread");
+        let raw_output = strip_thread_id.into_owned().leak();
         let info = parse_stdout(raw_output).unwrap();
         Cache { raw_output, info }
     };
@@ -36,9 +40,13 @@ lazy_static! {
 
 #[test]
 fn snapshot_testing_for_parsed_output() {
-    let ParsedCargoTestOutput { head, tree, detail } = &parsed_cargo_test().info[0].parsed;
+    let ParsedCargoTestOutput {
+        head, tree, detail, ..
+    } = &parsed_cargo_test().info[0].parsed;
     shot!(head, @"running 8 tests");
-    // tree is sorted when parsing
+    // test order is in random, and the tree keeps it when parsing, so sort it here
+    let mut tree = tree.clone();
+    tree.sort_unstable();
     snap!(tree, @r###"
     [
         "test submod::ignore ... ignored, reason",
@@ -73,16 +81,16 @@ fn snapshot_testing_for_parsed_output() {
     failure_info.sort_unstable();
     snap!(failure_info, @r###"
     [
-        "---- submod::panic::panicked stdout ----\nthread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:\nexplicit panic",
-        "---- submod::panic::should_panic_but_didnt stdout ----\nnote: test did not panic as expected",
+        "---- submod::panic::panicked stdout ----\n\nthread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:\nexplicit panic",
+        "---- submod::panic::should_panic_but_didnt stdout ----\nnote: test did not panic as expected at tests/integration/src/lib.rs:22:12",
     ]
     "###);
 }
 
 #[test]
 fn snapshot_testing_for_pretty_output() {
-    let lines = parsed_cargo_test().info[0].parsed.tree.iter().copied();
-    shot!(make_pretty("test", lines).unwrap(), @r###"
+    let tests = &parsed_cargo_test().info[0].parsed.tests;
+    shot!(make_pretty("test", tests, &Options::default()), @r###"
     test
     ├── submod
     │   ├─ 🔕 ignore
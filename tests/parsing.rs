@@ -1,9 +1,28 @@
 use cargo_pretty_test::{
-    fetch::parse_cargo_test_output,
-    parsing::{parse_cargo_test_with_empty_ones, TestType},
+    args::Args,
+    config::{merge, parse_toml, to_args},
+    diagnostic::parse_build_failure,
+    events::events_to_text,
+    failure::parse_failures,
+    fetch::{make_test_tree, parse_cargo_test_output, slowest_report},
+    history::{compare, load_run, run_ids, save_run, Record},
+    junit::junit_report,
+    markdown::{markdown_build_failure, markdown_report},
+    output::attribute_nocapture,
+    parsing::{
+        parse_cargo_test, parse_cargo_test_with_empty_ones, parse_stderr, parse_stdout,
+        split_combined_output, Src, TestType,
+    },
+    prettify::{make_pretty, Options, Sort, Status, StatusFilter},
+    rerun::{failed_tests, filter_args, load_failed, rerun_args, save_failed, Metadata},
+    stream::Incremental,
+    theme::Theme,
+    watch::Watcher,
+    Error,
 };
-use insta::assert_display_snapshot;
+use insta::{assert_debug_snapshot, assert_display_snapshot};
 use pretty_assertions::assert_eq;
+use std::time::Duration;
 
 const STDERR: &str = "\
     Finished test [unoptimized + debuginfo] target(s) in 0.00s
@@ -136,3 +155,1678 @@ fn display_test_tree() {
         "total time in running all tests should be {total_time}"
     );
 }
+
+#[test]
+fn runner_paths() {
+    let stderr = "     Running unittests src/lib.rs (/tmp/x86_64-build/debug/deps/cargo_pretty_test-9b4400a4dee777d5)
+     Running unittests src/main.rs (target/x86_64-unknown-linux-musl/release/deps/cargo_pretty_test-269f1bfba2d44b88)
+     Running tests/parsing.rs (target\\my-target\\ci\\deps\\parsing-bd11dfdabc9464fa.exe)
+   Doc-tests cargo_pretty_test";
+    let mut src: Vec<_> = parse_stderr(stderr)
+        .unwrap()
+        .into_iter()
+        .map(|runner| runner.src)
+        .collect();
+    assert!(src.iter().all(|src| src.target.is_none()));
+    for src in &mut src {
+        src.set_target(&["x86_64-unknown-linux-musl", "my-target"]);
+    }
+    assert_eq!(
+        src,
+        [
+            Src {
+                src_path: "src/lib.rs",
+                bin_name: "cargo_pretty_test",
+                bin_path: Some("/tmp/x86_64-build/debug/deps/cargo_pretty_test-9b4400a4dee777d5"),
+                target: None,
+                profile: Some("debug"),
+            },
+            Src {
+                src_path: "src/main.rs",
+                bin_name: "cargo_pretty_test",
+                bin_path: Some(
+                    "target/x86_64-unknown-linux-musl/release/deps/cargo_pretty_test-269f1bfba2d44b88"
+                ),
+                target: Some("x86_64-unknown-linux-musl"),
+                profile: Some("release"),
+            },
+            Src {
+                src_path: "tests/parsing.rs",
+                bin_name: "parsing",
+                bin_path: Some("target\\my-target\\ci\\deps\\parsing-bd11dfdabc9464fa.exe"),
+                target: Some("my-target"),
+                profile: Some("ci"),
+            },
+            Src {
+                src_path: "cargo_pretty_test",
+                bin_name: "cargo_pretty_test",
+                bin_path: None,
+                target: None,
+                profile: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn target_values() {
+    let args = Args::parse(["--target", "a", "--target=b.json", "--", "--target", "c"]).unwrap();
+    assert_eq!(args.cargo_values("target"), ["a", "b.json"]);
+}
+
+#[test]
+fn parsing_errors() {
+    assert_eq!(parse_stdout("no tests").unwrap_err(), Error::NoTestRunning);
+    let stdout = "\nrunning 2 tests\ntest a ... ok\n\ntest result: ok. 1 passed; 0 failed; \
+                  0 ignored; 0 measured; 0 filtered out; finished in 0.00s\n";
+    let err = parse_stdout(stdout).unwrap_err();
+    assert_eq!(
+        err,
+        Error::TotalMismatch {
+            offset: 1,
+            line: "running 2 tests".into(),
+            head: 2,
+            parsed: 1
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "`running 2 tests` at byte 1 of stdout expects 2 tests, but 1 are found"
+    );
+    assert_eq!(
+        parse_stdout("running 1 test\ntest a ... ok\n").unwrap_err(),
+        Error::MissingStats {
+            offset: 0,
+            line: "running 1 test".into()
+        }
+    );
+    assert_eq!(
+        parse_stdout("warning\nrunning 1 test\ntest a ... ok\n").unwrap_err(),
+        Error::MissingStats {
+            offset: 8,
+            line: "running 1 test".into()
+        }
+    );
+
+    let stderr =
+        "     Running unittests src/lib.rs (target/debug/deps/cargo_pretty_test-9b4400a4dee777d5)
+     Running unittests src/lib.rs (cargo_pretty_test)";
+    assert_eq!(
+        parse_stderr(stderr).unwrap_err(),
+        Error::InvalidBinPath {
+            offset: 89,
+            line: "Running unittests src/lib.rs (cargo_pretty_test)".into()
+        }
+    );
+    assert_eq!(
+        parse_cargo_test_with_empty_ones(STDERR, STDOUT_FAILED)
+            .err()
+            .unwrap(),
+        Error::RunnerCountMismatch {
+            stderr: 5,
+            stdout: 2
+        }
+    );
+    assert_eq!(
+        parse_cargo_test_with_empty_ones("", STDOUT_FAILED)
+            .err()
+            .unwrap(),
+        Error::NoTestRunner
+    );
+}
+
+const STDERR_FAILED: &str =
+    "     Running unittests src/lib.rs (target/debug/deps/integration-a99bdd97562f9052)
+   Doc-tests integration\
+";
+
+const STDOUT_FAILED: &str = "
+running 4 tests
+test submod::ignore ... ignored, reason
+test submod::normal_test ... ok
+test submod::panic::panicked ... FAILED
+test submod::panic::should_panic_but_didnt - should panic ... FAILED
+
+failures:
+
+---- submod::panic::panicked stdout ----
+thread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:
+explicit panic
+
+---- submod::panic::should_panic_but_didnt stdout ----
+note: test did not panic as expected
+
+failures:
+    submod::panic::panicked
+    submod::panic::should_panic_but_didnt
+
+test result: FAILED. 1 passed; 2 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.01s
+
+
+running 2 tests
+test tests/integration/src/lib.rs - attribute::ignore (line 76) ... ignored
+test tests/integration/src/lib.rs - attribute::no_run (line 86) - compile ... ok
+
+test result: ok. 1 passed; 0 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.20s
+";
+
+#[test]
+fn junit_xml_report() {
+    let runners = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    assert_display_snapshot!(junit_report(&runners), @r###"
+    <?xml version="1.0" encoding="UTF-8"?>
+    <testsuites name="cargo-pretty-test" tests="6" failures="2" skipped="2" time="0.210">
+      <testsuite name="integration (src/lib.rs)" package="integration" tests="4" failures="2" skipped="1" time="0.010">
+        <testcase name="submod::ignore" classname="integration (src/lib.rs)">
+          <skipped message="reason"/>
+        </testcase>
+        <testcase name="submod::normal_test" classname="integration (src/lib.rs)"/>
+        <testcase name="submod::panic::panicked" classname="integration (src/lib.rs)">
+          <failure message="explicit panic">thread &apos;submod::panic::panicked&apos; panicked at tests/integration/src/lib.rs:11:13:
+    explicit panic</failure>
+        </testcase>
+        <testcase name="submod::panic::should_panic_but_didnt" classname="integration (src/lib.rs)">
+          <failure message="test did not panic as expected">note: test did not panic as expected</failure>
+        </testcase>
+      </testsuite>
+      <testsuite name="integration" package="Doc Tests" tests="2" failures="0" skipped="1" time="0.200">
+        <testcase name="tests/integration/src/lib.rs - attribute::ignore (line 76)" classname="integration">
+          <skipped/>
+        </testcase>
+        <testcase name="tests/integration/src/lib.rs - attribute::no_run (line 86)" classname="integration"/>
+      </testsuite>
+    </testsuites>
+
+    "###);
+}
+
+#[test]
+fn failure_details() {
+    let detail = r#"failures:
+
+---- submod::assert stdout ----
+captured line
+thread 'submod::assert' (4242) panicked at src/lib.rs:20:9:
+assertion `left == right` failed: values differ
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+---- submod::old_panic stdout ----
+thread 'submod::old_panic' panicked at 'assertion failed: `(left == right)`
+  left: `"a"`,
+ right: `"b"`', src/lib.rs:30:5
+
+---- submod::should_panic_but_didnt stdout ----
+note: test did not panic as expected at src/lib.rs:40:4
+
+failures:
+    submod::assert
+    submod::old_panic
+    submod::should_panic_but_didnt"#;
+    assert_debug_snapshot!(parse_failures(detail), @r###"
+    [
+        Failure {
+            name: "submod::assert",
+            stdout: "captured line\nthread 'submod::assert' (4242) panicked at src/lib.rs:20:9:\nassertion `left == right` failed: values differ\n  left: 1\n right: 2\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace",
+            thread: Some(
+                "submod::assert",
+            ),
+            message: "assertion `left == right` failed: values differ\n  left: 1\n right: 2",
+            location: Some(
+                Location {
+                    file: "src/lib.rs",
+                    line: 20,
+                    col: 9,
+                },
+            ),
+            left: Some(
+                "1",
+            ),
+            right: Some(
+                "2",
+            ),
+        },
+        Failure {
+            name: "submod::old_panic",
+            stdout: "thread 'submod::old_panic' panicked at 'assertion failed: `(left == right)`\n  left: `\"a\"`,\n right: `\"b\"`', src/lib.rs:30:5",
+            thread: Some(
+                "submod::old_panic",
+            ),
+            message: "assertion failed: `(left == right)`\n  left: `\"a\"`,\n right: `\"b\"`",
+            location: Some(
+                Location {
+                    file: "src/lib.rs",
+                    line: 30,
+                    col: 5,
+                },
+            ),
+            left: Some(
+                "\"a\"",
+            ),
+            right: Some(
+                "\"b\"",
+            ),
+        },
+        Failure {
+            name: "submod::should_panic_but_didnt",
+            stdout: "note: test did not panic as expected at src/lib.rs:40:4",
+            thread: None,
+            message: "test did not panic as expected",
+            location: Some(
+                Location {
+                    file: "src/lib.rs",
+                    line: 40,
+                    col: 4,
+                },
+            ),
+            left: None,
+            right: None,
+        },
+    ]
+    "###);
+}
+
+#[test]
+fn inline_failures() {
+    let runners = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    let opts = Options {
+        inline_failures: true,
+        ..Options::default()
+    };
+    assert_display_snapshot!(make_test_tree(&runners, &opts).0, @r###"
+    Generated by cargo-pretty-test
+    ├── (FAIL) integration ... (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)
+    │   └── (FAIL) src/lib.rs ... (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)
+    │       └── submod
+    │           ├─ 🔕 ignore
+    │           ├─ ✅ normal_test
+    │           └── panic
+    │               ├─ ❌ panicked
+    │               │   ├── explicit panic
+    │               │   └── at tests/integration/src/lib.rs:11:13
+    │               └─ ❌ should_panic_but_didnt - should panic
+    │                   └── test did not panic as expected
+    └── (OK) Doc Tests ... (2 tests in 0.20s: ✅ 1; 🔕 1)
+        └── (OK) integration ... (2 tests in 0.20s: ✅ 1; 🔕 1)
+            └── tests/integration/src/lib.rs - attribute
+                ├─ 🔕 ignore (line 76)
+                └─ ✅ no_run (line 86) - compile
+
+    "###);
+
+    let stdout = "
+running 1 test
+test submod::assert ... FAILED
+
+failures:
+
+---- submod::assert stdout ----
+thread 'submod::assert' panicked at src/lib.rs:20:9:
+assertion `left == right` failed
+  left: 1
+ right: 2
+
+failures:
+    submod::assert
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+";
+    let info = parse_stdout(stdout).unwrap().pop().unwrap();
+    let opts = Options {
+        inline_failures: true,
+        failure_lines: 2,
+        ..Options::default()
+    };
+    let tree = make_pretty("test", &info.parsed.tests, &opts);
+    assert_display_snapshot!(tree, @r###"
+    test
+    └── submod
+        └─ ❌ assert
+            ├── assertion `left == right` failed
+            ├──   left: 1
+            ├── ... 1 more line
+            └── at src/lib.rs:20:9
+
+    "###);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+#[allow(clippy::too_many_lines)]
+fn json_report() {
+    let runners = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    assert_display_snapshot!(cargo_pretty_test::fetch::json_report(&runners).unwrap(), @r###"
+    {
+      "stats": {
+        "ok": false,
+        "total": 6,
+        "passed": 2,
+        "failed": 2,
+        "flaky": 0,
+        "ignored": 2,
+        "measured": 0,
+        "filtered_out": 0,
+        "finished_in": 0.21
+      },
+      "pkgs": [
+        {
+          "name": "integration",
+          "runners": [
+            {
+              "ty": "UnitLib",
+              "src": {
+                "src_path": "src/lib.rs",
+                "bin_name": "integration",
+                "bin_path": "target/debug/deps/integration-a99bdd97562f9052",
+                "target": null,
+                "profile": "debug"
+              },
+              "stats": {
+                "ok": false,
+                "total": 4,
+                "passed": 1,
+                "failed": 2,
+                "flaky": 0,
+                "ignored": 1,
+                "measured": 0,
+                "filtered_out": 0,
+                "finished_in": 0.01
+              },
+              "head": "running 4 tests",
+              "tests": [
+                {
+                  "name": "submod::ignore",
+                  "status": "ignored",
+                  "ignore_reason": "reason",
+                  "should_panic": false,
+                  "doc_line": null,
+                  "duration": null,
+                  "failure": null,
+                  "output": null,
+                  "change": null
+                },
+                {
+                  "name": "submod::normal_test",
+                  "status": "ok",
+                  "ignore_reason": null,
+                  "should_panic": false,
+                  "doc_line": null,
+                  "duration": null,
+                  "failure": null,
+                  "output": null,
+                  "change": null
+                },
+                {
+                  "name": "submod::panic::panicked",
+                  "status": "failed",
+                  "ignore_reason": null,
+                  "should_panic": false,
+                  "doc_line": null,
+                  "duration": null,
+                  "failure": {
+                    "name": "submod::panic::panicked",
+                    "stdout": "thread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:\nexplicit panic",
+                    "thread": "submod::panic::panicked",
+                    "message": "explicit panic",
+                    "location": {
+                      "file": "tests/integration/src/lib.rs",
+                      "line": 11,
+                      "col": 13
+                    },
+                    "left": null,
+                    "right": null
+                  },
+                  "output": null,
+                  "change": null
+                },
+                {
+                  "name": "submod::panic::should_panic_but_didnt",
+                  "status": "failed",
+                  "ignore_reason": null,
+                  "should_panic": true,
+                  "doc_line": null,
+                  "duration": null,
+                  "failure": {
+                    "name": "submod::panic::should_panic_but_didnt",
+                    "stdout": "note: test did not panic as expected",
+                    "thread": null,
+                    "message": "test did not panic as expected",
+                    "location": null,
+                    "left": null,
+                    "right": null
+                  },
+                  "output": null,
+                  "change": null
+                }
+              ],
+              "detail": "failures:\n\n---- submod::panic::panicked stdout ----\nthread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:\nexplicit panic\n\n---- submod::panic::should_panic_but_didnt stdout ----\nnote: test did not panic as expected\n\nfailures:\n    submod::panic::panicked\n    submod::panic::should_panic_but_didnt"
+            }
+          ],
+          "stats": {
+            "ok": false,
+            "total": 4,
+            "passed": 1,
+            "failed": 2,
+            "flaky": 0,
+            "ignored": 1,
+            "measured": 0,
+            "filtered_out": 0,
+            "finished_in": 0.01
+          }
+        },
+        {
+          "name": "Doc Tests",
+          "runners": [
+            {
+              "ty": "Doc",
+              "src": {
+                "src_path": "integration",
+                "bin_name": "integration",
+                "bin_path": null,
+                "target": null,
+                "profile": null
+              },
+              "stats": {
+                "ok": true,
+                "total": 2,
+                "passed": 1,
+                "failed": 0,
+                "flaky": 0,
+                "ignored": 1,
+                "measured": 0,
+                "filtered_out": 0,
+                "finished_in": 0.2
+              },
+              "head": "running 2 tests",
+              "tests": [
+                {
+                  "name": "tests/integration/src/lib.rs - attribute::ignore (line 76)",
+                  "status": "ignored",
+                  "ignore_reason": null,
+                  "should_panic": false,
+                  "doc_line": 76,
+                  "duration": null,
+                  "failure": null,
+                  "output": null,
+                  "change": null
+                },
+                {
+                  "name": "tests/integration/src/lib.rs - attribute::no_run (line 86)",
+                  "status": "ok",
+                  "ignore_reason": null,
+                  "should_panic": false,
+                  "doc_line": 86,
+                  "duration": null,
+                  "failure": null,
+                  "output": null,
+                  "change": null
+                }
+              ],
+              "detail": ""
+            }
+          ],
+          "stats": {
+            "ok": true,
+            "total": 2,
+            "passed": 1,
+            "failed": 0,
+            "flaky": 0,
+            "ignored": 1,
+            "measured": 0,
+            "filtered_out": 0,
+            "finished_in": 0.2
+          }
+        }
+      ]
+    }
+    "###);
+}
+
+const EVENTS: &str = r#"
+{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "submod::ignore" }
+{ "type": "test", "name": "submod::ignore", "event": "ignored", "message": "reason" }
+{ "type": "test", "event": "started", "name": "submod::normal_test" }
+printed by a test with --nocapture
+{ "type": "test", "name": "submod::normal_test", "event": "ok", "exec_time": 0.000000888 }
+{ "type": "test", "event": "started", "name": "submod::panic::panicked" }
+{ "type": "test", "name": "submod::panic::panicked", "event": "failed", "exec_time": 0.016048111, "stdout": "\nthread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:\nexplicit panic\n" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 1, "measured": 0, "filtered_out": 0, "exec_time": 0.017043573 }
+"#;
+
+#[test]
+fn libtest_json_events() {
+    let text = events_to_text(EVENTS);
+    assert_display_snapshot!(text.trim(), @r###"
+    running 3 tests
+    test submod::ignore ... ignored, reason
+    test submod::normal_test ... ok <0.000000888s>
+    test submod::panic::panicked ... FAILED <0.016048111s>
+
+    successes:
+
+    ---- submod::normal_test stdout ----
+    printed by a test with --nocapture
+
+
+    successes:
+
+    failures:
+
+    ---- submod::panic::panicked stdout ----
+
+    thread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:
+    explicit panic
+
+    failures:
+        submod::panic::panicked
+
+    test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.017043573s
+    "###);
+
+    let info = parse_stdout(&text).unwrap();
+    let durations: Vec<_> = info[0].parsed.tests.iter().map(|t| t.duration).collect();
+    assert_eq!(
+        durations,
+        [
+            None,
+            Some(Duration::from_nanos(888)),
+            Some(Duration::from_nanos(16_048_111))
+        ]
+    );
+    assert_eq!(info[0].stats.finished_in, Duration::from_nanos(17_043_573));
+}
+
+#[test]
+fn durations_and_slowest() {
+    let stderr =
+        "     Running unittests src/lib.rs (target/debug/deps/integration-a99bdd97562f9052)";
+    let stdout = events_to_text(EVENTS);
+    let runners = parse_cargo_test(stderr, &stdout).unwrap();
+    assert_display_snapshot!(make_test_tree(&runners, &Options::default()).0, @r###"
+    Generated by cargo-pretty-test
+    └── (FAIL) integration ... (3 tests in 0.02s: ✅ 1; ❌ 1; 🔕 1)
+        └── (FAIL) src/lib.rs ... (3 tests in 0.02s: ✅ 1; ❌ 1; 🔕 1)
+            └── submod
+                ├─ 🔕 ignore
+                ├─ ✅ normal_test <0.000s>
+                │   └── printed by a test with --nocapture
+                └── panic
+                    └─ ❌ panicked <0.016s>
+
+    "###);
+    assert_display_snapshot!(slowest_report(&runners, 5), @r###"
+    Slowest 2 tests:
+      <0.016s> integration src/lib.rs submod::panic::panicked
+      <0.000s> integration src/lib.rs submod::normal_test
+    "###);
+    assert_display_snapshot!(junit_report(&runners), @r###"
+    <?xml version="1.0" encoding="UTF-8"?>
+    <testsuites name="cargo-pretty-test" tests="3" failures="1" skipped="1" time="0.017">
+      <testsuite name="integration (src/lib.rs)" package="integration" tests="3" failures="1" skipped="1" time="0.017">
+        <testcase name="submod::ignore" classname="integration (src/lib.rs)">
+          <skipped message="reason"/>
+        </testcase>
+        <testcase name="submod::normal_test" classname="integration (src/lib.rs)" time="0.000"/>
+        <testcase name="submod::panic::panicked" classname="integration (src/lib.rs)" time="0.016">
+          <failure message="explicit panic">thread &apos;submod::panic::panicked&apos; panicked at tests/integration/src/lib.rs:11:13:
+    explicit panic</failure>
+        </testcase>
+      </testsuite>
+    </testsuites>
+
+    "###);
+}
+
+#[test]
+fn incremental_runners() {
+    let mut parser = Incremental::default();
+    let mut runners = Vec::new();
+    let mut stdout = STDOUT.lines();
+    for line in STDERR.lines() {
+        parser.push_stderr(line);
+        // feed stdout until a test runner finishes
+        for line in stdout.by_ref() {
+            parser.push_stdout(line);
+            if line.starts_with("test result:") {
+                break;
+            }
+        }
+        runners.extend(parser.pop());
+    }
+    assert_eq!(parser.pop(), None);
+
+    let parsed: Vec<_> = runners
+        .iter()
+        .map(|(err, out)| {
+            let runner = &parse_stderr(err).unwrap()[0];
+            let info = &parse_stdout(out).unwrap()[0];
+            (runner.src.src_path, info.stats.total)
+        })
+        .collect();
+    assert_eq!(
+        parsed,
+        [
+            ("src/lib.rs", 0),
+            ("src/main.rs", 0),
+            ("tests/golden_master_test.rs", 1),
+            ("tests/mocking_project.rs", 2),
+            ("cargo-pretty-test", 14),
+        ]
+    );
+}
+
+#[test]
+fn parse_combined_output() {
+    let (block_lib, block_doc) = STDOUT_FAILED.split_at(STDOUT_FAILED.find("\nrunning 2").unwrap());
+    let combined = format!(
+        "   Compiling integration v0.0.0 (/root/crate/tests/integration)\r\n\
+         \x20   Finished test [unoptimized + debuginfo] target(s) in 0.50s\r\n\
+         \x20    Running unittests src/lib.rs (target/debug/deps/integration-a99bdd97562f9052)\n\
+         {block_lib}\n\
+         error: test failed, to rerun pass `-p integration --lib`\n\
+         \x20  \x1b[1m\x1b[32mDoc-tests\x1b[0m integration\n\
+         {block_doc}\n\
+         error: 1 target failed:\n\
+         \x20   `-p integration --lib`\n"
+    );
+    let (stderr, stdout) = split_combined_output(&combined);
+    assert_display_snapshot!(stderr, @r###"
+       Compiling integration v0.0.0 (/root/crate/tests/integration)
+        Finished test [unoptimized + debuginfo] target(s) in 0.50s
+         Running unittests src/lib.rs (target/debug/deps/integration-a99bdd97562f9052)
+    error: test failed, to rerun pass `-p integration --lib`
+       Doc-tests integration
+    error: 1 target failed:
+        `-p integration --lib`
+
+    "###);
+
+    let combined = parse_cargo_test(&stderr, &stdout).unwrap();
+    let separate = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    assert_eq!(junit_report(&combined), junit_report(&separate));
+
+    // lines printed by tests with --nocapture only look like compiler diagnostics
+    let (stderr, stdout) = split_combined_output(
+        "warning: unused variable: `y`\n\
+         \x20--> src/main.rs:2:9\n\
+         \n\
+         running 1 test\n\
+         \x20 = total\n\
+         1 | 2\n\
+         \x20--> next\n\
+         test prints ... ok\n",
+    );
+    assert_eq!(
+        stderr,
+        "warning: unused variable: `y`\n --> src/main.rs:2:9\n"
+    );
+    assert_eq!(
+        stdout,
+        "\nrunning 1 test\n  = total\n1 | 2\n --> next\ntest prints ... ok\n"
+    );
+}
+
+#[test]
+fn build_failure() {
+    let log = "   Compiling integration v0.1.0 (/root/cargo-pretty-test/tests/integration)
+warning: unused variable: `y`
+ --> src/main.rs:2:9
+  |
+2 |     let y = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_y`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+error[E0425]: cannot find value `x` in this scope
+ --> src/lib.rs:1:21
+  |
+1 | pub fn f() -> u32 { x }
+  |                     ^ not found in this scope
+
+error[E0308]: mismatched types
+ --> src/lib.rs:2:27
+  |
+2 | pub fn g() { let y: u32 = \"a\"; }
+  |                     ---   ^^^ expected `u32`, found `&str`
+  |                     |
+  |                     expected due to this
+
+Some errors have detailed explanations: E0308, E0425.
+For more information about an error, try `rustc --explain E0308`.
+error: could not compile `integration` (lib test) due to 2 previous errors
+warning: build failed, waiting for other jobs to finish...
+error[E0599]: no method named `foo` found for unit type `()` in the current scope
+ --> tests/parsing.rs:3:8
+  |
+3 |     ().foo();
+  |        ^^^ method not found in `()`
+
+error: could not compile `integration` (test \"parsing\") due to 1 previous error
+";
+    let (stderr, stdout) = split_combined_output(log);
+    assert!(stdout.trim().is_empty(), "{stdout:?}");
+    let failure = parse_build_failure(&stderr).unwrap();
+    assert_display_snapshot!(failure.make_tree(), @r###"
+    Build failed
+    ├── integration (lib test)
+    │   ├── error[E0425]: cannot find value `x` in this scope
+    │   │   └── at src/lib.rs:1:21
+    │   └── error[E0308]: mismatched types
+    │       └── at src/lib.rs:2:27
+    └── integration (test "parsing")
+        └── error[E0599]: no method named `foo` found for unit type `()` in the current scope
+            └── at tests/parsing.rs:3:8
+
+    "###);
+    assert_display_snapshot!(markdown_build_failure(&failure), @r###"
+    ## ❌ cargo-pretty-test: Build failed
+
+    ```text
+    Build failed
+    ├── integration (lib test)
+    │   ├── error[E0425]: cannot find value `x` in this scope
+    │   │   └── at src/lib.rs:1:21
+    │   └── error[E0308]: mismatched types
+    │       └── at src/lib.rs:2:27
+    └── integration (test "parsing")
+        └── error[E0599]: no method named `foo` found for unit type `()` in the current scope
+            └── at tests/parsing.rs:3:8
+    ```
+
+    "###);
+    assert!(parse_build_failure(STDERR_FAILED).is_none());
+}
+
+#[test]
+fn doctest_compile_error() {
+    // compiler errors in the stdout block of a doc test don't fail the build
+    let log = "   Compiling dtf v0.1.0 (/tmp/dtf)
+    Finished `test` profile [unoptimized + debuginfo] target(s) in 0.00s
+     Running unittests src/lib.rs (target/debug/deps/dtf-0123456789abcdef)
+
+running 1 test
+test works ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+   Doc-tests dtf
+
+running 1 test
+test src/lib.rs - add (line 1) ... FAILED
+
+failures:
+
+---- src/lib.rs - add (line 1) stdout ----
+error[E0425]: cannot find value `y` in this scope
+ --> src/lib.rs:3:10
+  |
+3 | dtf::add(y, 1);
+  |          ^ not found in this scope
+
+error: aborting due to 1 previous error
+
+For more information about this error, try `rustc --explain E0425`.
+Couldn't compile the test.
+
+failures:
+    src/lib.rs - add (line 1)
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+
+error: doctest failed, to rerun pass `--doc`
+";
+    let (stderr, stdout) = split_combined_output(log);
+    assert!(!stderr.contains("E0425"), "{stderr:?}");
+    assert!(parse_build_failure(&stderr).is_none());
+    let runners = parse_cargo_test(&stderr, &stdout).unwrap();
+    let doc = &runners.pkgs[1].inner[0].info.parsed.tests[0];
+    let failure = doc.failure.as_ref().unwrap();
+    assert!(failure.stdout.starts_with("error[E0425]"), "{failure:?}");
+    assert_display_snapshot!(make_test_tree(&runners, &Options::default()).0, @r###"
+    Generated by cargo-pretty-test
+    ├── (OK) dtf ... (1 tests in 0.00s: ✅ 1)
+    │   └── (OK) src/lib.rs ... (1 tests in 0.00s: ✅ 1)
+    │       └─ ✅ works
+    └── (FAIL) Doc Tests ... (1 tests in 0.00s: ❌ 1)
+        └── (FAIL) dtf ... (1 tests in 0.00s: ❌ 1)
+            └─ ❌ src/lib.rs - add (line 1)
+
+    "###);
+}
+
+const METADATA: &str = r#"{"packages":[{"name":"integration","targets":[
+{"kind":["lib"],"name":"integration","src_path":"/root/cargo-pretty-test/tests/integration/src/lib.rs"},
+{"kind":["bin"],"name":"integration","src_path":"/root/cargo-pretty-test/tests/integration/src/main.rs"},
+{"kind":["test"],"name":"my-parsing","src_path":"/root/cargo-pretty-test/tests/integration/tests/my-parsing.rs"}]}],
+"target_directory":"/root/cargo-pretty-test/target"}"#;
+
+#[test]
+fn rerun_failed_tests() {
+    let runners = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    let mut failed = failed_tests(&runners);
+    let dir = std::env::temp_dir().join(format!("cargo-pretty-test-{}", std::process::id()));
+    save_failed(&dir, &failed).unwrap();
+    assert_eq!(load_failed(&dir).unwrap(), failed);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(load_failed(&dir).unwrap().is_empty());
+
+    let mut doc = failed[0].clone();
+    doc.ty = TestType::Doc;
+    doc.src_path = "integration".into();
+    doc.name = "src/lib.rs - attribute::should_panic (line 80)".into();
+    let mut test = failed[0].clone();
+    test.ty = TestType::Tests;
+    test.src_path = "tests/my-parsing.rs".into();
+    test.bin_name = "my_parsing".into();
+    failed.extend([doc, test]);
+
+    let meta = Metadata::parse(METADATA).unwrap();
+    let forward = ["--release", "--", "--test-threads=1"].map(String::from);
+    let args: Vec<_> = rerun_args(&meta, &failed, &forward)
+        .into_iter()
+        .map(|args| args.join(" "))
+        .collect();
+    assert_eq!(
+        args,
+        [
+            "--release -p integration --lib -- --test-threads=1 --exact \
+             submod::panic::panicked submod::panic::should_panic_but_didnt",
+            "--release -p integration --doc -- --test-threads=1 attribute::should_panic",
+            "--release -p integration --test my-parsing -- --test-threads=1 --exact \
+             submod::panic::panicked",
+        ]
+    );
+
+    // the package selection is replaced by the one of the target
+    let forward = [
+        "--workspace",
+        "--exclude",
+        "other",
+        "-p",
+        "integration",
+        "--package=other",
+        "--release",
+        "--",
+        "-p",
+    ]
+    .map(String::from);
+    let args = filter_args(&meta, &failed[0], None, false, &forward).unwrap();
+    assert_eq!(args.join(" "), "--release -p integration --lib -- -p");
+    let args = rerun_args(&meta, &failed[..1], &forward);
+    assert_eq!(
+        args[0].join(" "),
+        "--release -p integration --lib -- -p --exact submod::panic::panicked"
+    );
+}
+
+#[test]
+fn flaky_tests() {
+    let mut runners = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    runners.mark_flaky(|_, test| test.name == "submod::panic::panicked");
+    let lib = &runners.pkgs[&Some("integration")].inner[0];
+    assert_eq!(lib.info.stats.failed, 1);
+    assert_eq!(lib.info.stats.flaky, 1);
+    assert!(!lib.info.stats.ok);
+
+    let (tree, stats) = make_test_tree(&runners, &Options::default());
+    assert_display_snapshot!(format!("{tree}\n{stats}"), @r###"
+    Generated by cargo-pretty-test
+    ├── (FAIL) integration ... (4 tests in 0.01s: ✅ 1; ❌ 1; 🔁 1; 🔕 1)
+    │   └── (FAIL) src/lib.rs ... (4 tests in 0.01s: ✅ 1; ❌ 1; 🔁 1; 🔕 1)
+    │       └── submod
+    │           ├─ 🔕 ignore
+    │           ├─ ✅ normal_test
+    │           └── panic
+    │               ├─ 🔁 panicked
+    │               └─ ❌ should_panic_but_didnt - should panic
+    └── (OK) Doc Tests ... (2 tests in 0.20s: ✅ 1; 🔕 1)
+        └── (OK) integration ... (2 tests in 0.20s: ✅ 1; 🔕 1)
+            └── tests/integration/src/lib.rs - attribute
+                ├─ 🔕 ignore (line 76)
+                └─ ✅ no_run (line 86) - compile
+
+    Status: FAIL; total 6 tests in 0.21s: 2 passed; 1 failed; 1 flaky; 2 ignored; 0 measured; 0 filtered out
+    "###);
+    assert!(junit_report(&runners).contains(
+        "<flakyFailure message=\"explicit panic\">thread &apos;submod::panic::panicked&apos;"
+    ));
+
+    // passing on retry makes the runner ok
+    runners.mark_flaky(|_, test| test.name == "submod::panic::should_panic_but_didnt");
+    assert!(runners.stats().ok);
+    assert_eq!(runners.stats().flaky, 2);
+}
+
+#[test]
+fn compare_runs() {
+    let mut runners = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    let dir =
+        std::env::temp_dir().join(format!("cargo-pretty-test-history-{}", std::process::id()));
+    let id = save_run(&dir, &runners).unwrap();
+    assert_eq!(run_ids(&dir).unwrap(), [id.as_str()]);
+    let mut base = load_run(&dir, "last").unwrap();
+    assert_eq!(base.id, id);
+    assert_eq!(base.records.len(), 6);
+    assert!(load_run(&dir, "0").is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let record = |name: &str| base.records.iter().position(|r| r.name == name).unwrap();
+    let (panicked, normal, no_run) = (
+        record("submod::panic::panicked"),
+        record("submod::normal_test"),
+        record("tests/integration/src/lib.rs - attribute::no_run (line 86)"),
+    );
+    base.records[panicked].status = Status::Ok;
+    base.records[normal].duration = Some(Duration::from_millis(100));
+    base.records.remove(no_run);
+    base.records.push(Record {
+        pkg: "integration".into(),
+        src_path: "src/lib.rs".into(),
+        name: "submod::gone".into(),
+        status: Status::Ok,
+        duration: None,
+    });
+    runners.pkgs[&Some("integration")].inner[0]
+        .info
+        .parsed
+        .tests[1]
+        .duration = Some(Duration::from_secs(1));
+
+    base.id = "1".into();
+    let diff = compare(&mut runners, &base);
+    let (tree, stats) = make_test_tree(&runners, &Options::default());
+    assert_display_snapshot!(format!("{tree}\n{stats}\n{diff}"), @r###"
+    Generated by cargo-pretty-test
+    ├── (FAIL) integration ... (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)
+    │   └── (FAIL) src/lib.rs ... (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)
+    │       └── submod
+    │           ├─ 🔕 ignore
+    │           ├─ ✅ normal_test <1.000s> [slower than 0.100s]
+    │           └── panic
+    │               ├─ ❌ panicked [newly failing]
+    │               └─ ❌ should_panic_but_didnt - should panic
+    └── (OK) Doc Tests ... (2 tests in 0.20s: ✅ 1; 🔕 1)
+        └── (OK) integration ... (2 tests in 0.20s: ✅ 1; 🔕 1)
+            └── tests/integration/src/lib.rs - attribute
+                ├─ 🔕 ignore (line 76)
+                └─ ✅ no_run (line 86) - compile [new]
+
+    Status: FAIL; total 6 tests in 0.21s: 2 passed; 2 failed; 2 ignored; 0 measured; 0 filtered out
+    Compared with run 1: 1 newly failing; 0 newly passing; 1 new; 1 removed; 1 slower
+      removed integration src/lib.rs submod::gone
+    "###);
+}
+
+#[test]
+fn watch_changes() {
+    let dir = std::env::temp_dir().join(format!("cargo-pretty-test-watch-{}", std::process::id()));
+    let src = dir.join("src");
+    let mut watcher = Watcher::new(vec![src.clone(), dir.join("tests")]);
+    assert!(!watcher.changed());
+
+    std::fs::create_dir_all(src.join("submod")).unwrap();
+    std::fs::write(src.join("submod/lib.rs"), "").unwrap();
+    assert!(watcher.changed());
+    assert!(!watcher.changed());
+
+    // files outside the watched directories are ignored
+    std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+    assert!(!watcher.changed());
+
+    std::fs::remove_file(src.join("submod/lib.rs")).unwrap();
+    assert!(watcher.changed());
+
+    // changes before the reset are taken as the baseline
+    std::fs::write(src.join("written-by-test.txt"), "").unwrap();
+    watcher.reset();
+    assert!(!watcher.changed());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "tui")]
+#[test]
+fn tui_tree() {
+    use cargo_pretty_test::tui::{Action, App, KeyCode};
+
+    let runners = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    let mut app = App::new(&runners, Vec::new(), false);
+    assert_display_snapshot!(app.tree_lines().join("\n"), @r###"
+    ▾ ❌ integration
+      ▾ ❌ src/lib.rs
+        ▾ ❌ submod
+            🔕 ignore
+            ✅ normal_test
+          ▾ ❌ panic
+              ❌ panicked
+              ❌ should_panic_but_didnt - should panic
+    ▾ ✅ Doc Tests
+      ▸ ✅ integration
+    "###);
+
+    // select the first failed test and show its failure
+    for _ in 0..6 {
+        app.press(KeyCode::Down, 10);
+    }
+    assert_display_snapshot!(app.detail_lines().join("\n"), @r###"
+    submod::panic::panicked
+
+    status: failed
+
+    explicit panic
+    at tests/integration/src/lib.rs:11:13
+
+    output:
+      thread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:
+      explicit panic
+    "###);
+
+    app.press(KeyCode::Char('s'), 10);
+    app.press(KeyCode::Char('/'), 10);
+    for c in "should".chars() {
+        app.press(KeyCode::Char(c), 10);
+    }
+    app.press(KeyCode::Enter, 10);
+    assert_display_snapshot!(app.tree_lines().join("\n"), @r###"
+    ▾ ❌ integration
+      ▾ ❌ src/lib.rs
+        ▾ ❌ submod
+          ▾ ❌ panic
+              ❌ should_panic_but_didnt - should panic
+    "###);
+
+    assert_eq!(app.press(KeyCode::Char('r'), 10), Action::Rerun);
+    assert_eq!(app.press(KeyCode::Esc, 10), Action::Continue);
+    assert_eq!(app.press(KeyCode::Char('q'), 10), Action::Quit);
+}
+
+#[test]
+fn filter_by_status() {
+    let runners = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    let only = |list| Options {
+        statuses: StatusFilter::parse(list).unwrap(),
+        ..Options::default()
+    };
+
+    // runners and packages without failed tests are dropped, but the summary counts all tests
+    let (tree, stats) = make_test_tree(&runners, &only("failed"));
+    assert_display_snapshot!(format!("{tree}\n{stats}"), @r###"
+    Generated by cargo-pretty-test
+    └── (FAIL) integration ... (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)
+        └── (FAIL) src/lib.rs ... (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)
+            └── submod
+                └── panic
+                    ├─ ❌ panicked
+                    └─ ❌ should_panic_but_didnt - should panic
+
+    Status: FAIL; total 6 tests in 0.21s: 2 passed; 2 failed; 2 ignored; 0 measured; 0 filtered out
+    "###);
+
+    let hide_passed = StatusFilter::ALL.without(Status::Ok);
+    assert_eq!(
+        only("failed, ignored,flaky"),
+        Options {
+            statuses: hide_passed,
+            ..Options::default()
+        }
+    );
+    let (tree, _) = make_test_tree(&runners, &only("ignored"));
+    assert_display_snapshot!(tree, @r###"
+    Generated by cargo-pretty-test
+    ├── (FAIL) integration ... (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)
+    │   └── (FAIL) src/lib.rs ... (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)
+    │       └── submod
+    │           └─ 🔕 ignore
+    └── (OK) Doc Tests ... (2 tests in 0.20s: ✅ 1; 🔕 1)
+        └── (OK) integration ... (2 tests in 0.20s: ✅ 1; 🔕 1)
+            └── tests/integration/src/lib.rs - attribute
+                └─ 🔕 ignore (line 76)
+
+    "###);
+
+    assert_eq!(
+        StatusFilter::parse("passed,skipped").unwrap_err(),
+        "unknown status `skipped`, expected one of passed, failed, ignored, flaky"
+    );
+}
+
+#[test]
+fn collapse_passing() {
+    let runners = parse_cargo_test(STDERR, STDOUT).unwrap();
+    let opts = Options {
+        collapse_passing: true,
+        ..Options::default()
+    };
+    // modules and runners whose tests all passed are single lines
+    let (tree, _) = make_test_tree(&runners, &opts);
+    assert_display_snapshot!(tree, @r###"
+    Generated by cargo-pretty-test
+    ├── (OK) cargo_pretty_test ... (3 tests in 0.03s: ✅ 3)
+    │   ├── (OK) tests/golden_master_test.rs ... (1 tests in 0.01s: ✅ 1)
+    │   └── (OK) tests/mocking_project.rs ... (2 tests in 0.02s: ✅ 2)
+    └── (OK) Doc Tests ... (14 tests in 0.00s: ✅ 12; ❌ 1; 🔕 1)
+        └── (OK) cargo-pretty-test ... (14 tests in 0.00s: ✅ 12; ❌ 1; 🔕 1)
+            ├── src/lib.rs - attribute
+            │   ├─ ✅ edition2018 (line 100)
+            │   ├─ 🔕 ignore (line 76)
+            │   ├─ ✅ no_run (line 86) - compile
+            │   ├─ ✅ should_compile_fail (line 90) - compile fail
+            │   ├─ ❌ should_compile_fail_but_didnt (line 96) - compile fail
+            │   └─ ✅ should_panic (line 80)
+            ├─ ✅ src/lib.rs - empty_doc_mod (2 tests)
+            ├─ ✅ src/lib.rs - empty_doc_mod (line 41)
+            ├─ ✅ src/lib.rs - normal_doc_mod (3 tests)
+            ├─ ✅ src/lib.rs - normal_doc_mod (line 55)
+            └─ ✅ tests/integration/src/lib.rs - (line 1)
+
+    "###);
+}
+
+#[test]
+fn module_counts() {
+    let runners = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    let opts = Options {
+        module_counts: true,
+        ..Options::default()
+    };
+    let (tree, _) = make_test_tree(&runners, &opts);
+    assert_display_snapshot!(tree, @r###"
+    Generated by cargo-pretty-test
+    ├── (FAIL) integration ... (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)
+    │   └── (FAIL) src/lib.rs ... (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)
+    │       └── submod (4 tests: ✅ 1; ❌ 2; 🔕 1)
+    │           ├─ 🔕 ignore
+    │           ├─ ✅ normal_test
+    │           └── panic (2 tests: ❌ 2)
+    │               ├─ ❌ panicked
+    │               └─ ❌ should_panic_but_didnt - should panic
+    └── (OK) Doc Tests ... (2 tests in 0.20s: ✅ 1; 🔕 1)
+        └── (OK) integration ... (2 tests in 0.20s: ✅ 1; 🔕 1)
+            └── tests/integration/src/lib.rs - attribute (2 tests: ✅ 1; 🔕 1)
+                ├─ 🔕 ignore (line 76)
+                └─ ✅ no_run (line 86) - compile
+
+    "###);
+}
+
+#[test]
+fn configuration() {
+    let file = parse_toml(
+        r#"
+# shared by the team
+only = ["failed", 'ignored']
+collapse-passing = true # trailing comment
+junit = "target/junit.xml"
+"#,
+    )
+    .unwrap();
+    let meta = Metadata::parse(
+        r#"{"packages":[],"target_directory":"/target","workspace_metadata":
+        {"pretty-test":{"slowest":10,"only":"failed","inline-failures":false}}}"#,
+    )
+    .unwrap();
+    let workspace = meta.workspace_metadata.unwrap().get("pretty-test").cloned();
+
+    // the file takes precedence over the workspace metadata
+    let configured = to_args(&merge(&[workspace.unwrap(), file])).unwrap();
+    assert_eq!(
+        configured,
+        [
+            "--slowest",
+            "10",
+            "--only",
+            "failed,ignored",
+            "--collapse-passing",
+            "--junit",
+            "target/junit.xml"
+        ]
+    );
+
+    // options on the command line override the configured ones
+    let mut args = Args::parse(["--slowest=3", "--no-collapse-passing", "--", "--junit"]).unwrap();
+    args.add_defaults(Args::parse(configured).unwrap(), &[]);
+    assert_eq!(args.value("slowest"), Some("3"));
+    assert_eq!(args.value("only"), Some("failed,ignored"));
+    assert_eq!(args.value("junit"), Some("target/junit.xml"));
+    assert!(!args.flag("collapse-passing"));
+    assert_eq!(args.forward(), ["--", "--junit"]);
+
+    assert_display_snapshot!(parse_toml("only = [\"failed\" \"ignored\"]").unwrap_err(), @r###"
+    TOML parse error at line 1, column 18
+      |
+    1 | only = ["failed" "ignored"]
+      |                  ^
+    invalid array
+    expected `]`
+    "###);
+    let invalid = |text| to_args(&parse_toml(text).unwrap()).unwrap_err();
+    assert_eq!(
+        invalid("[pretty-test]\nonly = \"failed\""),
+        "unknown key `pretty-test` in the configuration"
+    );
+    assert_eq!(
+        invalid("color = true"),
+        "unknown key `color` in the configuration"
+    );
+    assert_eq!(invalid("stream = 1"), "`stream` should be true or false");
+}
+
+#[test]
+fn command_line_args() {
+    let args = Args::parse([
+        "--workspace",
+        "--pretty-only",
+        "failed",
+        "--format=json",
+        "--pretty-collapse-passing",
+        "--test",
+        "parsing",
+        "--no-fail-fast",
+        "--",
+        "--format",
+        "terse",
+    ])
+    .unwrap();
+    assert_eq!(
+        args,
+        Args {
+            own: vec![
+                ("only", Some("failed".into())),
+                ("format", Some("json".into())),
+                ("collapse-passing", None),
+            ],
+            cargo: ["--workspace", "--test", "parsing", "--no-fail-fast"]
+                .map(String::from)
+                .into(),
+            test_bins: ["--format", "terse"].map(String::from).into(),
+            ..Args::default()
+        }
+    );
+    assert_eq!(
+        args.forward(),
+        [
+            "--workspace",
+            "--test",
+            "parsing",
+            "--no-fail-fast",
+            "--",
+            "--format",
+            "terse"
+        ]
+    );
+
+    let help = Args::parse(["-h", "--pretty-no-watch"]).unwrap();
+    assert!(help.help && !help.version);
+    assert_eq!(help.negated, ["watch"]);
+
+    let err = |args: &[&str]| Args::parse(args.iter().copied()).unwrap_err();
+    assert_eq!(
+        err(&["--pretty-colour"]),
+        "unknown option `--pretty-colour`"
+    );
+    assert_eq!(
+        err(&["--pretty-slowest"]),
+        "`--pretty-slowest` requires a value"
+    );
+    assert_eq!(err(&["--watch=yes"]), "`watch` doesn't take a value");
+}
+
+const STDERR_CAPTURED: &str =
+    "     Running unittests src/lib.rs (target/debug/deps/cap-0123456789abcdef)";
+
+#[test]
+fn captured_output() {
+    let nocapture = "
+running 3 tests
+test tests::fails ... before failing
+FAILED
+test tests::prints ... hello from prints
+second line
+ok
+test tests::quiet ... ok
+
+failures:
+
+failures:
+    tests::fails
+
+test result: FAILED. 2 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+";
+    let show_output = attribute_nocapture(nocapture);
+    assert_display_snapshot!(show_output.trim(), @r###"
+    running 3 tests
+    test tests::fails ... FAILED
+    test tests::prints ... ok
+    test tests::quiet ... ok
+
+
+    successes:
+
+    ---- tests::prints stdout ----
+    hello from prints
+    second line
+
+
+    successes:
+
+    failures:
+
+    ---- tests::fails stdout ----
+    before failing
+
+
+    failures:
+        tests::fails
+
+    test result: FAILED. 2 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+    "###);
+
+    let events = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "tests::fails" }
+before failing
+{ "type": "test", "name": "tests::fails", "event": "failed", "exec_time": 0.019 }
+{ "type": "test", "event": "started", "name": "tests::prints" }
+{ "type": "test", "name": "tests::prints", "event": "ok", "exec_time": 0.001, "stdout": "hello from prints\nsecond line\n" }
+{ "type": "test", "event": "started", "name": "tests::quiet" }
+{ "type": "test", "name": "tests::quiet", "event": "ok", "exec_time": 0.001 }
+{ "type": "suite", "event": "failed", "passed": 2, "failed": 1, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 0.02 }"#;
+    let events = events_to_text(events);
+    for stdout in [&*show_output, &events] {
+        let runners = parse_cargo_test(STDERR_CAPTURED, stdout).unwrap();
+        let info = &runners.pkgs[0].inner[0].info;
+        let prints = &info.parsed.tests[1];
+        assert_eq!(prints.output, Some("hello from prints\nsecond line"));
+        let fails = &info.parsed.tests[0];
+        assert_eq!(fails.failure.as_ref().unwrap().stdout, "before failing");
+        // the successes section isn't left in the detail printed above the tree
+        assert!(!info.parsed.detail.contains("successes:"));
+    }
+
+    let runners = parse_cargo_test(STDERR_CAPTURED, &events).unwrap();
+    let (tree, _) = make_test_tree(&runners, &Options::default());
+    assert_display_snapshot!(tree, @r###"
+    Generated by cargo-pretty-test
+    └── (FAIL) cap ... (3 tests in 0.02s: ✅ 2; ❌ 1)
+        └── (FAIL) src/lib.rs ... (3 tests in 0.02s: ✅ 2; ❌ 1)
+            └── tests
+                ├─ ❌ fails <0.019s>
+                ├─ ✅ prints <0.001s>
+                │   ├── hello from prints
+                │   └── second line
+                └─ ✅ quiet <0.001s>
+
+    "###);
+}
+
+#[test]
+fn captured_partial_lines() {
+    // output without a trailing line break is followed by the status on the same line
+    let nocapture = "
+running 4 tests
+test tests::fails ... before failingFAILED
+test tests::partial ... partialok
+test tests::prints ... hello
+lookok
+test tests::quiet ... ok
+
+failures:
+
+failures:
+    tests::fails
+
+test result: FAILED. 3 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+";
+    let show_output = attribute_nocapture(nocapture);
+    assert_display_snapshot!(show_output.trim(), @r###"
+    running 4 tests
+    test tests::fails ... FAILED
+    test tests::partial ... ok
+    test tests::prints ... ok
+    test tests::quiet ... ok
+
+
+    successes:
+
+    ---- tests::partial stdout ----
+    partial
+
+
+    ---- tests::prints stdout ----
+    hello
+    look
+
+
+    successes:
+
+    failures:
+
+    ---- tests::fails stdout ----
+    before failing
+
+
+    failures:
+        tests::fails
+
+    test result: FAILED. 3 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+    "###);
+
+    let runners = parse_cargo_test(STDERR_CAPTURED, &show_output).unwrap();
+    let tests = &runners.pkgs[0].inner[0].info.parsed.tests;
+    assert_eq!(tests[0].failure.as_ref().unwrap().stdout, "before failing");
+    assert_eq!(tests[1].output, Some("partial"));
+    assert_eq!(tests[2].output, Some("hello\nlook"));
+    assert_eq!(tests[3].output, None);
+}
+
+#[test]
+fn icon_themes() {
+    // the emoji theme is in use
+    assert_eq!(Status::Failed.icon(), "─ ❌ ");
+    assert_eq!(Status::Failed.symbol(), "❌");
+    #[allow(deprecated)]
+    let notation = cargo_pretty_test::prettify::ICON_NOTATION;
+    assert_eq!(Theme::default().notation(), notation);
+
+    let ascii = Theme::named("ascii").unwrap();
+    assert_eq!(ascii.icon(Status::Ok), "[PASS]");
+    assert_eq!(ascii.indent(Status::Ignored), "─ [SKIP] ");
+    assert_display_snapshot!(ascii.notation().trim(), @r###"
+    Icon Notation:
+    ─ [PASS] pass (including the case that should panic and did panic)
+    ─ [FAIL] fail (including the case that should panic but didn't panic)
+    ─ [FLAKY] flaky (failed but passed on retry with --retries)
+    ─ [SKIP] ignored (with reason omitted)
+    ─ [FILTERED] filtered out (won't show in the test tree, but will be computed in the summary)
+    "###);
+
+    // icons set one by one after a theme, as in `icons = ["ascii", "flaky=[RETRY]"]`
+    let configured = to_args(&parse_toml(r#"icons = ["ascii", "flaky=[RETRY]"]"#).unwrap());
+    let args = Args::parse(configured.unwrap()).unwrap();
+    let custom = Theme::parse(args.value("icons").unwrap()).unwrap();
+    assert_eq!(custom.indent(Status::Flaky), "─ [RETRY] ");
+    assert_eq!(custom.icon(Status::Failed), "[FAIL]");
+    assert_eq!(Theme::parse("failed=x").unwrap().icon(Status::Ok), "✅");
+    assert_eq!(Theme::parse("emoji").unwrap(), Theme::default());
+
+    assert_eq!(
+        Theme::parse("ascii,skipped=-").unwrap_err(),
+        "unknown status `skipped`, expected passed, failed, flaky, ignored or filtered"
+    );
+    assert_eq!(
+        Theme::parse("unicode").unwrap_err(),
+        "unknown theme `unicode`, expected one of emoji, ascii, nerdfont or `<status>=<icon>`"
+    );
+}
+
+#[test]
+fn sort_orders() {
+    let stdout = "
+running 5 tests
+test zeta::slow ... ok <0.300s>
+test alpha::fast ... ok <0.010s>
+test beta ... ignored
+test alpha::broken ... FAILED <0.020s>
+test gamma ... ok
+
+failures:
+
+failures:
+    alpha::broken
+
+test result: FAILED. 3 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.33s
+";
+    let info = parse_stdout(stdout).unwrap();
+    let tree = |sort| {
+        let opts = Options {
+            sort,
+            ..Options::default()
+        };
+        make_pretty("lib", &info[0].parsed.tests, &opts)
+    };
+    assert_display_snapshot!(tree("name".parse().unwrap()), @r###"
+    lib
+    ├── alpha
+    │   ├─ ❌ broken <0.020s>
+    │   └─ ✅ fast <0.010s>
+    ├─ 🔕 beta
+    ├─ ✅ gamma
+    └── zeta
+        └─ ✅ slow <0.300s>
+
+    "###);
+    // failed ones first, and a module takes the most notable status of its tests
+    assert_display_snapshot!(tree(Sort::Status), @r###"
+    lib
+    ├── alpha
+    │   ├─ ❌ broken <0.020s>
+    │   └─ ✅ fast <0.010s>
+    ├─ ✅ gamma
+    ├── zeta
+    │   └─ ✅ slow <0.300s>
+    └─ 🔕 beta
+
+    "###);
+    // slowest first, and a module takes the total duration of its tests
+    assert_display_snapshot!(tree(Sort::Duration), @r###"
+    lib
+    ├── zeta
+    │   └─ ✅ slow <0.300s>
+    ├── alpha
+    │   ├─ ❌ broken <0.020s>
+    │   └─ ✅ fast <0.010s>
+    ├─ 🔕 beta
+    └─ ✅ gamma
+
+    "###);
+    assert_display_snapshot!(tree(Sort::Declaration), @r###"
+    lib
+    ├── zeta
+    │   └─ ✅ slow <0.300s>
+    ├── alpha
+    │   ├─ ✅ fast <0.010s>
+    │   └─ ❌ broken <0.020s>
+    ├─ 🔕 beta
+    └─ ✅ gamma
+
+    "###);
+    assert_eq!(
+        "random".parse::<Sort>().unwrap_err(),
+        "unknown order `random`, expected one of name, status, duration, declaration"
+    );
+}
+
+#[test]
+fn markdown() {
+    let runners = parse_cargo_test(STDERR_FAILED, STDOUT_FAILED).unwrap();
+    assert_display_snapshot!(markdown_report(&runners, &Options::default()), @r###"
+    ## ❌ cargo-pretty-test: FAIL
+
+    Status: FAIL; total 6 tests in 0.21s: 2 passed; 2 failed; 2 ignored; 0 measured; 0 filtered out
+
+    | Package | Status | Tests | Passed | Failed | Flaky | Ignored | Filtered out | Time |
+    | --- | :---: | ---: | ---: | ---: | ---: | ---: | ---: | ---: |
+    | integration | ❌ | 4 | 1 | 2 | 0 | 1 | 0 | 0.01s |
+    | Doc Tests | ✅ | 2 | 1 | 0 | 0 | 1 | 0 | 0.20s |
+
+    <details open>
+    <summary>❌ integration: src/lib.rs (4 tests in 0.01s: ✅ 1; ❌ 2; 🔕 1)</summary>
+
+    ```text
+    src/lib.rs
+    └── submod
+        ├─ 🔕 ignore
+        ├─ ✅ normal_test
+        └── panic
+            ├─ ❌ panicked
+            └─ ❌ should_panic_but_didnt - should panic
+    ```
+
+    </details>
+
+    <details>
+    <summary>✅ Doc Tests: integration (2 tests in 0.20s: ✅ 1; 🔕 1)</summary>
+
+    ```text
+    integration
+    └── tests/integration/src/lib.rs - attribute
+        ├─ 🔕 ignore (line 76)
+        └─ ✅ no_run (line 86) - compile
+    ```
+
+    </details>
+
+    ### Failures
+
+    #### ❌ `integration src/lib.rs`: `submod::panic::panicked`
+
+    ```text
+    thread 'submod::panic::panicked' panicked at tests/integration/src/lib.rs:11:13:
+    explicit panic
+    ```
+
+    #### ❌ `integration src/lib.rs`: `submod::panic::should_panic_but_didnt`
+
+    ```text
+    note: test did not panic as expected
+    ```
+
+    "###);
+}
+
+#[test]
+fn markdown_without_failure_blocks() {
+    let stdout = "
+running 2 tests
+test a ... FAILED
+test b ... FAILED
+
+failures:
+output without blocks
+
+failures:
+    a
+    b
+
+test result: FAILED. 0 passed; 2 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+";
+    let runners = parse_cargo_test(STDERR_CAPTURED, stdout).unwrap();
+    let md = markdown_report(&runners, &Options::default());
+    // the detail of the runner is shown once for all the tests
+    let failures = &md[md.find("### Failures").unwrap()..];
+    assert_display_snapshot!(failures, @r###"
+    ### Failures
+
+    #### ❌ `cap src/lib.rs`: `a`, `b`
+
+    ```text
+    failures:
+    output without blocks
+
+    failures:
+        a
+        b
+    ```
+
+    "###);
+}